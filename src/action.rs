@@ -1,7 +1,12 @@
-use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use serde::{
+    Deserialize, Serialize,
+    de::{self, Deserializer, IntoDeserializer},
+};
 use strum::Display;
 
-use crate::components::home::HomeAction;
+use crate::{app::Mode, components::home::HomeAction};
 
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum AppAction {
@@ -12,8 +17,15 @@ pub enum AppAction {
     Resume,
     Quit,
     ClearScreen,
+    ReloadConfig,
     Error(String),
     Help,
+    /// quit in pick mode and print the given paths
+    Pick(Vec<PathBuf>),
+    /// the directory the shell changes to after quitting
+    ChangeDir(PathBuf),
+    /// switch to another set of keybindings
+    SetMode(Mode),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,3 +45,84 @@ impl From<HomeAction> for Action {
         Action::Home(action)
     }
 }
+
+/// Parse an action from its name as it is used in the keybindings of the config.
+///
+/// Global actions are referenced by their name (e.g. `Quit` or `App.Quit`), component
+/// actions are prefixed with the name of their component (e.g. `Home.Expand`).
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let action = match s.split_once('.') {
+            None | Some(("App", _)) => {
+                let name = s.strip_prefix("App.").unwrap_or(s);
+                AppAction::deserialize(name.into_deserializer()).map(Action::App)
+            }
+            Some(("Home", name)) => {
+                HomeAction::deserialize(name.into_deserializer()).map(Action::Home)
+            }
+            Some((component, _)) => return Err(format!("unknown component `{component}`")),
+        };
+        action.map_err(|err: de::value::Error| err.to_string())
+    }
+}
+
+/// Format an action the same way it is parsed by its [`FromStr`] implementation.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::App(action) => write!(f, "{action}"),
+            Action::Home(action) => write!(f, "Home.{action}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_app_action() {
+        assert_eq!("Quit".parse(), Ok(Action::App(AppAction::Quit)));
+        assert_eq!("App.Suspend".parse(), Ok(Action::App(AppAction::Suspend)));
+    }
+
+    #[test]
+    fn test_parse_component_action() {
+        assert_eq!("Home.Expand".parse(), Ok(Action::Home(HomeAction::Expand)));
+        assert_eq!(
+            "Home.SelectLast".parse(),
+            Ok(Action::Home(HomeAction::SelectLast))
+        );
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for action in [
+            Action::App(AppAction::Quit),
+            Action::Home(HomeAction::Collapse),
+        ] {
+            assert_eq!(action.to_string().parse(), Ok(action));
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_action() {
+        assert!("Expand".parse::<Action>().is_err());
+        assert!("Home.Quit".parse::<Action>().is_err());
+        assert!("Unknown.Quit".parse::<Action>().is_err());
+    }
+}
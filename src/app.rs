@@ -1,66 +1,108 @@
+use std::path::{Path, PathBuf};
+
 use color_eyre::Result;
 use crossterm::event::KeyEvent;
 use ratatui::prelude::Rect;
 use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
 use tokio::sync::mpsc;
-use tracing::debug;
+use tracing::{debug, error, info, warn};
 
 use crate::{
     action::{Action, AppAction},
-    arena::Arena,
     cli::Cli,
     components::{Component, fps::FpsCounter, home::Home},
-    config::Config,
-    terminal::{Terminal, events::TermEvent},
+    config::{Config, ConfigWatcher, get_config_dir, keys::key_event_to_string},
+    terminal::{Terminal, TerminalOutput, events::TermEvent},
+    tree::FileTree,
 };
 
+/// The maximum number of actions which are handled between two terminal events.
+const MAX_ACTIONS_PER_FRAME: usize = 1000;
+
 pub struct App {
     config: Config,
-    arena: Arena<String>,
     tick_rate: f64,
     frame_rate: f64,
+    /// whether the screen is redrawn on every render tick instead of only after changes
+    fixed_rate: bool,
     fullscreen: bool,
     inline_height: u16,
+    /// whether the app was started with `--pick`
+    pick: bool,
+    picked: Option<Vec<PathBuf>>,
+    /// whether the view is rendered to the terminal because stdout is used for the output
+    render_to_tty: bool,
+    cwd: Option<PathBuf>,
     ui_components: Vec<Box<dyn Component>>,
     should_quit: bool,
+    /// whether the screen has to be redrawn for a reason outside of the components
+    needs_render: bool,
     mode: Mode,
     last_tick_key_events: Vec<KeyEvent>,
     action_tx: mpsc::UnboundedSender<Action>,
     action_rx: mpsc::UnboundedReceiver<Action>,
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize,
+)]
+// the config crate lowercases all keys of the config files, which includes the modes
+#[strum(ascii_case_insensitive)]
 pub enum Mode {
     #[default]
     Home,
+    /// a search query is typed in the home view
+    Search,
+    /// filter patterns are typed in the home view
+    Filter,
+    /// the order of the entries is changed in the home view
+    Sort,
+    /// the destination of an extraction from an archive is typed in the home view
+    Extract,
 }
 
 impl App {
     pub fn new(args: Cli) -> Result<Self> {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let tree = FileTree::new(&args.root()?)?;
 
-        return Ok(Self {
-            arena: Arena::new(),
+        Ok(Self {
             tick_rate: args.tick_rate,
             frame_rate: args.frame_rate,
-            fullscreen: args.fullscreen,
+            fixed_rate: args.fixed_rate,
+            // the inline viewport queries its position on stdout, which is not the terminal
+            // when it is used for the output
+            fullscreen: args.fullscreen || args.writes_to_stdout(),
             inline_height: args.inline_height,
-            ui_components: vec![Box::new(Home::new()), Box::new(FpsCounter::default())],
+            pick: args.pick,
+            picked: None,
+            render_to_tty: args.writes_to_stdout(),
+            cwd: None,
+            ui_components: vec![Box::new(Home::new(tree)), Box::new(FpsCounter::default())],
             should_quit: false,
+            needs_render: true,
             config: Config::new()?,
             mode: Mode::Home,
             last_tick_key_events: Vec::new(),
             action_tx,
             action_rx,
-        });
+        })
     }
 
     pub async fn run(&mut self) -> Result<()> {
+        // keep stdout free for the output
+        let output = if self.render_to_tty {
+            TerminalOutput::tty()?
+        } else {
+            TerminalOutput::stdout()
+        };
         let mut terminal = Terminal::new(
             self.tick_rate,
             self.frame_rate,
             self.fullscreen,
             self.inline_height,
+            output,
         )?;
         terminal.enter()?;
 
@@ -70,6 +112,11 @@ impl App {
             component.init(terminal.size()?)?;
         }
 
+        // the config directory is watched until this is dropped at the end of `run`
+        let _config_watcher = ConfigWatcher::new(&get_config_dir(), self.action_tx.clone())
+            .inspect_err(|err| warn!("Configuration changes are not watched: {}", err))
+            .ok();
+
         loop {
             self.handle_terminal_events(&mut terminal).await?;
             self.handle_actions(&mut terminal)?;
@@ -119,7 +166,7 @@ impl App {
 
         match keymap.get(&vec![key]) {
             Some(action) => {
-                action_tx.send(action.clone().into())?;
+                action_tx.send(action.clone())?;
             }
             _ => {
                 // If the key was not handled as a single key action,
@@ -128,15 +175,33 @@ impl App {
 
                 // Check for multi-key combinations
                 if let Some(action) = keymap.get(&self.last_tick_key_events) {
-                    action_tx.send(action.clone().into())?;
+                    action_tx.send(action.clone())?;
+                } else {
+                    let sequence: Vec<String> = self
+                        .last_tick_key_events
+                        .iter()
+                        .map(key_event_to_string)
+                        .collect();
+                    debug!("No keybinding for {}", sequence.join(" "));
                 }
             }
         }
         Ok(())
     }
 
+    /// Handle the queued actions, including the ones they lead to, until the queue is empty.
+    ///
+    /// At most [`MAX_ACTIONS_PER_FRAME`] actions are handled at once, so a component that
+    /// keeps sending actions (e.g. while loading a huge directory) cannot starve the terminal
+    /// events. The rest is handled after the next event. Once the app quits, the queue is
+    /// emptied regardless, so the components can report back, e.g. the last directory.
     fn handle_actions(&mut self, terminal: &mut Terminal) -> Result<()> {
-        while let Ok(action) = self.action_rx.try_recv() {
+        let mut handled = 0;
+        while handled < MAX_ACTIONS_PER_FRAME || self.should_quit {
+            let Ok(action) = self.action_rx.try_recv() else {
+                break;
+            };
+            handled += 1;
             if let Action::App(app_action) = &action {
                 if *app_action != AppAction::Tick && *app_action != AppAction::Render {
                     debug!("Emitted action: {:?}", app_action);
@@ -147,9 +212,27 @@ impl App {
                         self.last_tick_key_events.drain(..);
                     }
                     AppAction::Quit => self.should_quit = true,
-                    AppAction::ClearScreen => terminal.terminal.clear()?,
+                    AppAction::Pick(paths) if self.pick => {
+                        self.picked = Some(paths.clone());
+                        self.should_quit = true;
+                    }
+                    AppAction::ChangeDir(path) => self.cwd = Some(path.clone()),
+                    AppAction::SetMode(mode) => {
+                        self.mode = *mode;
+                        self.last_tick_key_events.clear();
+                    }
+                    AppAction::Suspend => self.suspend(terminal)?,
+                    AppAction::Resume => {
+                        terminal.resume()?;
+                        self.render(terminal)?;
+                    }
+                    AppAction::ClearScreen => {
+                        terminal.terminal.clear()?;
+                        self.needs_render = true;
+                    }
+                    AppAction::ReloadConfig => self.reload_config()?,
                     AppAction::Resize(w, h) => self.handle_resize(terminal, *w, *h)?,
-                    AppAction::Render => self.render(terminal)?,
+                    AppAction::Render if self.should_render() => self.render(terminal)?,
                     _ => {}
                 }
             }
@@ -163,13 +246,65 @@ impl App {
         Ok(())
     }
 
+    pub fn is_pick_mode(&self) -> bool {
+        self.pick
+    }
+
+    /// The paths that were picked before quitting, if the app runs in pick mode.
+    pub fn picked(&self) -> Option<&[PathBuf]> {
+        self.picked.as_deref()
+    }
+
+    /// The directory of the selection when the app was quit.
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Hand the terminal back to the shell until the process receives `SIGCONT`.
+    fn suspend(&mut self, terminal: &mut Terminal) -> Result<()> {
+        terminal.suspend()?;
+        self.last_tick_key_events.clear();
+        self.action_tx.send(AppAction::Resume.into())?;
+        Ok(())
+    }
+
+    /// Load the configuration again and hand it to every component.
+    ///
+    /// Invalid entries fall back to their defaults just like on startup, but are only logged
+    /// because the terminal is in use.
+    fn reload_config(&mut self) -> Result<()> {
+        let (config, diagnostics) = Config::load(&get_config_dir())?;
+        for diagnostic in diagnostics {
+            error!("Invalid configuration: {}", diagnostic);
+        }
+
+        self.config = config;
+        self.last_tick_key_events.clear();
+        for component in self.ui_components.iter_mut() {
+            component.register_config_handler(self.config.clone())?;
+        }
+        info!("Reloaded the configuration");
+        Ok(())
+    }
+
     fn handle_resize(&mut self, terminal: &mut Terminal, w: u16, h: u16) -> Result<()> {
         terminal.resize(Rect::new(0, 0, w, h))?;
         self.render(terminal)?;
         Ok(())
     }
 
+    /// Whether the screen has to be redrawn on a render tick.
+    fn should_render(&self) -> bool {
+        self.fixed_rate
+            || self.needs_render
+            || self
+                .ui_components
+                .iter()
+                .any(|component| component.needs_render())
+    }
+
     fn render(&mut self, terminal: &mut Terminal) -> Result<()> {
+        self.needs_render = false;
         terminal.draw(|frame| {
             for component in self.ui_components.iter_mut() {
                 if let Err(err) = component.draw(frame, frame.area()) {
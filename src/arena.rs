@@ -75,9 +75,7 @@ impl<T> Arena<T> {
     }
 
     pub fn remove(&mut self, handle: Handle) -> Option<T> {
-        if self.get(&handle).is_none() {
-            return None;
-        }
+        self.get(&handle)?;
 
         let new_free_slot = Slot::<T>::Free {
             next_free: self.free_slot,
@@ -95,16 +93,18 @@ impl<T> Arena<T> {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
     pub fn get(&self, handle: &Handle) -> Option<&T> {
         if handle.index >= self.data.len() {
             return None;
         }
 
         match &self.data[handle.index] {
-            Slot::Occupied { generation, value } if *generation == handle.generation => {
-                return Some(value);
-            }
-            _ => return None,
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
         }
     }
 
@@ -114,10 +114,8 @@ impl<T> Arena<T> {
         }
 
         match &mut self.data[handle.index] {
-            Slot::Occupied { generation, value } if *generation == handle.generation => {
-                return Some(value);
-            }
-            _ => return None,
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
         }
     }
 }
@@ -212,7 +210,7 @@ mod tests {
 #[cfg(test)]
 mod proptest {
 
-    use crate::{Arena, Handle};
+    use super::{Arena, Handle};
     use proptest::prelude::*;
     use std::collections::HashMap;
 
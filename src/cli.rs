@@ -1,10 +1,50 @@
-use clap::Parser;
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
 
 use crate::config::{get_config_dir, get_data_dir};
 
 #[derive(Parser, Debug)]
-#[command(author, version = version(), about)]
+#[command(author, version = version(), about, subcommand_precedence_over_arg = true)]
+#[command(group(ArgGroup::new("output").args(["print", "format"]).multiple(true)))]
 pub struct Cli {
+    /// Directory to browse, defaults to the current directory
+    #[arg(value_name = "PATH")]
+    pub path: Option<PathBuf>,
+
+    /// Print the tree to stdout instead of starting the interactive view
+    #[arg(short, long, default_value_t = false)]
+    pub print: bool,
+
+    /// Print the tree to stdout in the given format instead of starting the interactive view
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// Maximum depth of directories to print below PATH
+    #[arg(short, long, value_name = "DEPTH", requires = "output")]
+    pub depth: Option<usize>,
+
+    /// Pick files in the interactive view and print their paths to stdout on exit
+    ///
+    /// The view is rendered to the controlling terminal, so the output can be used in pipelines
+    /// like `vim $(lsn --pick)`.
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    pub pick: bool,
+
+    /// Separate the picked paths with NUL instead of newline characters
+    #[arg(short = '0', long, default_value_t = false, requires = "pick")]
+    pub null: bool,
+
+    /// Write the directory of the selected entry to FILE on quit, or print it if FILE is `-`
+    ///
+    /// This is used by the shell integration to change the directory, see `lsn init`. It is
+    /// ignored when the tree is printed, as there is no selected entry then.
+    #[arg(long, value_name = "FILE")]
+    pub cwd_file: Option<PathBuf>,
+
     /// Tick rate, i.e. number of ticks per second
     #[arg(long, value_name = "FLOAT", default_value_t = 4.0)]
     pub tick_rate: f64,
@@ -13,6 +53,10 @@ pub struct Cli {
     #[arg(long, value_name = "FLOAT", default_value_t = 60.0)]
     pub frame_rate: f64,
 
+    /// Redraw at the frame rate even if nothing changed, for debugging
+    #[arg(long, default_value_t = false)]
+    pub fixed_rate: bool,
+
     /// Use the whole terminal as viewport
     #[arg(short, long, default_value_t = false)]
     pub fullscreen: bool,
@@ -20,6 +64,80 @@ pub struct Cli {
     /// Use the whole terminal as viewport
     #[arg(short, long, default_value_t = 50)]
     pub inline_height: u16,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the configuration and print the effective configuration
+    ///
+    /// Exits with a non-zero status if the configuration contains any error.
+    #[command(visible_alias = "check-config")]
+    Config {
+        /// Check this file instead of the config files in the config directory
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+
+        /// Format of the printed configuration
+        #[arg(long, value_enum, default_value_t = ConfigFormat::Json5)]
+        format: ConfigFormat,
+    },
+    /// Print the shell integration, which changes into the last browsed directory on quit
+    ///
+    /// Add `eval "$(lsn init bash)"` to ~/.bashrc, `eval "$(lsn init zsh)"` to ~/.zshrc or
+    /// `lsn init fish | source` to ~/.config/fish/config.fish.
+    Init {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Draw the tree like the `tree` command
+    #[default]
+    Tree,
+    /// A JSON array with an object for every entry
+    Json,
+    /// A JSON object for every entry on its own line
+    Ndjson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json5,
+    Toml,
+}
+
+impl Cli {
+    /// The format to print the tree in, if the interactive view should not be started.
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.format.or(self.print.then_some(OutputFormat::Tree))
+    }
+
+    /// Whether the output of lsn is written to stdout, so the interactive view has to be
+    /// rendered to the terminal directly.
+    pub fn writes_to_stdout(&self) -> bool {
+        self.pick || self.cwd_file.as_deref() == Some(Path::new("-"))
+    }
+
+    /// The directory to browse as an absolute path.
+    pub fn root(&self) -> io::Result<PathBuf> {
+        match &self.path {
+            Some(path) => path.canonicalize(),
+            None => env::current_dir(),
+        }
+    }
 }
 
 const VERSION_MESSAGE: &str = concat!(
@@ -48,3 +166,21 @@ Config directory: {config_dir_path}
 Data directory: {data_dir_path}"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_cwd_file_with_print() {
+        // the shell integration passes --cwd-file to every invocation
+        let cli = Cli::try_parse_from(["lsn", "--cwd-file", "x", "--print"]).unwrap();
+        assert_eq!(cli.cwd_file, Some(PathBuf::from("x")));
+        assert_eq!(cli.output_format(), Some(OutputFormat::Tree));
+
+        let cli = Cli::try_parse_from(["lsn", "--cwd-file", "x", "--format", "json"]).unwrap();
+        assert_eq!(cli.output_format(), Some(OutputFormat::Json));
+    }
+}
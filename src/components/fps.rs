@@ -22,6 +22,8 @@ pub struct FpsCounter {
     last_frame_update: Instant,
     frame_count: u32,
     frames_per_second: f64,
+
+    needs_render: bool,
 }
 
 impl Default for FpsCounter {
@@ -39,6 +41,7 @@ impl FpsCounter {
             last_frame_update: Instant::now(),
             frame_count: 0,
             frames_per_second: 0.0,
+            needs_render: true,
         }
     }
 
@@ -50,6 +53,7 @@ impl FpsCounter {
             self.ticks_per_second = self.tick_count as f64 / elapsed;
             self.last_tick_update = now;
             self.tick_count = 0;
+            self.needs_render = true;
         }
         Ok(())
     }
@@ -69,17 +73,21 @@ impl FpsCounter {
 
 impl Component for FpsCounter {
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
-        if let Action::App(app_action) = action {
-            match app_action {
-                AppAction::Tick => self.app_tick()?,
-                AppAction::Render => self.render_tick()?,
-                _ => {}
-            };
+        if let Action::App(AppAction::Tick) = action {
+            self.app_tick()?;
         }
         Ok(None)
     }
 
+    fn needs_render(&self) -> bool {
+        self.needs_render
+    }
+
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        // only frames which are actually drawn are counted
+        self.render_tick()?;
+        self.needs_render = false;
+
         let [top, _] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
         let message = format!(
             "{:.2} ticks/sec, {:.2} FPS",
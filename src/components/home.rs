@@ -1,49 +1,2686 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    path::{Path, PathBuf},
+};
+
 use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+use strum::Display;
 use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, warn};
 
 use super::Component;
-use crate::{action::Action, config::Config};
+use crate::{
+    action::{Action, AppAction},
+    app::Mode,
+    arena::Handle,
+    config::{Config, PREVIEW_WIDTHS, save_dir_sort, save_global_sort},
+    preview::{Preview, PreviewSettings, preview_in_background, read_preview},
+    tree::{
+        FileTree,
+        archive::{ArchivedEntry, extract, extract_in_background, list_in_background},
+        details::{Column, Columns, Details, human_size},
+        filter::{Filter, Pattern},
+        git::{self, GitStatus, GitStatuses},
+        ignore::IgnoredEntries,
+        loader::{LoadEvent, load_in_background, read_entries, read_in_background},
+        node::{LoadState, Node, NodeKind},
+        search::{Match, find_matches},
+        sort::{SortKey, SortOrder},
+        usage::{DiskUsage, UsageEvent, measure_in_background},
+        watch::TreeWatcher,
+    },
+};
+
+const SELECTED_STYLE: Style = Style::new()
+    .bg(Color::Rgb(50, 50, 50))
+    .add_modifier(Modifier::BOLD);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum HomeAction {
-    None,
+    SelectNext,
+    SelectPrev,
+    SelectFirst,
+    SelectLast,
+    Expand,
+    Collapse,
+    Toggle,
+    /// toggle the selected directory or pick the selected file
+    Open,
+    /// mark or unmark the selected entry for picking and move on to the next one
+    ToggleMark,
+    /// pick the marked entries or, if nothing is marked, the selected one
+    Pick,
+    /// read all loaded directories again
+    Refresh,
+    /// start typing a search query
+    StartSearch,
+    /// a character that was typed into the search query
+    #[serde(skip)]
+    SearchInput(char),
+    /// remove the last character of the search query
+    SearchBackspace,
+    /// also load and search the directories which were not expanded yet
+    SearchAll,
+    /// stop typing and keep the current match selected
+    ConfirmSearch,
+    /// stop the search, which also returns to where it started while the query is typed
+    CancelSearch,
+    NextMatch,
+    PrevMatch,
+    /// start typing the filter patterns, which begins with the ones that are active
+    StartFilter,
+    /// a character that was typed into the filter patterns
+    #[serde(skip)]
+    FilterInput(char),
+    /// remove the last character of the filter patterns
+    FilterBackspace,
+    /// stop typing and keep the typed filter patterns
+    ConfirmFilter,
+    /// stop typing and go back to the filter patterns from before
+    CancelFilter,
+    /// turn all filters off or on again
+    ToggleFilters,
+    /// show the ignored entries like any other or treat them as configured again
+    ToggleIgnored,
+    /// change the order of all directories
+    StartSort,
+    /// change the order of the directory of the selected entry
+    StartDirSort,
+    /// sort by the given key first, or reverse the order if it already is the first key
+    SortByName,
+    SortBySize,
+    SortByMtime,
+    SortByExtension,
+    SortByKind,
+    /// reverse the direction of the first key
+    ReverseSort,
+    ToggleDirsFirst,
+    ToggleSortCase,
+    /// go back to the configured order
+    ResetSort,
+    StopSort,
+    /// show or hide the detail columns beside the tree
+    ToggleColumns,
+    /// measure the disk usage of the selected directory, like `du` does
+    MeasureSize,
+    /// measure the disk usage of all visible directories
+    MeasureAll,
+    /// show or hide the preview of the selected entry
+    TogglePreview,
+    GrowPreview,
+    ShrinkPreview,
+    /// start typing where the selected archive or archived entry is extracted to
+    StartExtract,
+    /// a character that was typed into the destination of the extraction
+    #[serde(skip)]
+    ExtractInput(char),
+    ExtractBackspace,
+    ConfirmExtract,
+    CancelExtract,
+    /// entries of a directory that is loaded in the background
+    #[serde(skip)]
+    Loaded(LoadId, NodeBatch),
+    /// a directory was loaded completely, or could not be read if there is an error
+    #[serde(skip)]
+    LoadFinished(LoadId, Option<String>),
+    /// the entries of these directories changed on disk
+    #[serde(skip)]
+    DirsChanged(Vec<PathBuf>),
+    /// a fresh listing of an already loaded directory
+    #[serde(skip)]
+    Listed(Handle, NodeBatch),
+    /// the disk usage of a directory which is still measured in the background
+    #[serde(skip)]
+    Measuring(LoadId, DiskUsage),
+    /// the disk usage of a directory which was measured completely
+    #[serde(skip)]
+    Measured(LoadId, DiskUsage),
+    /// the preview of an entry which was read in the background
+    #[serde(skip)]
+    Previewed(Preview),
+    /// the entries of an archive which was listed in the background
+    #[serde(skip)]
+    ArchiveListed(LoadId, Vec<ArchivedEntry>),
+    /// the number of entries which were extracted to the path, or why the extraction failed
+    #[serde(skip)]
+    Extracted(PathBuf, Result<usize, String>),
+    /// the git status of the tree which was read in the background, if it is in a repository
+    #[serde(skip)]
+    GitRead(Option<GitStatuses>),
+}
+
+/// Identifies a single background load or measurement of a directory.
+///
+/// A directory can be loaded again after its previous load was cancelled, so the handle alone
+/// is not enough to tell apart entries that are still in flight from the cancelled load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadId {
+    dir: Handle,
+    id: u64,
+}
+
+/// Nodes which were read in the background, compared by their paths.
+#[derive(Debug, Clone)]
+pub struct NodeBatch(Vec<Node>);
+
+impl PartialEq for NodeBatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .map(|node| &node.path)
+            .eq(other.0.iter().map(|node| &node.path))
+    }
+}
+
+impl Eq for NodeBatch {}
+
+struct Load {
+    id: u64,
+    token: CancellationToken,
 }
 
 #[derive(Default)]
+struct Search {
+    query: String,
+    /// whether the query is typed right now
+    typing: bool,
+    /// whether directories which are not loaded yet are loaded to be searched as well
+    deep: bool,
+    /// the matching nodes in the order of the tree
+    matches: Vec<Match>,
+    /// the indices of the matched characters of every matching node
+    indices: HashMap<Handle, Vec<usize>>,
+    /// the selection from before the search, which is restored when it is cancelled
+    origin: Option<Handle>,
+}
+
+struct Filters {
+    /// the patterns from the config
+    configured: Vec<Pattern>,
+    /// the patterns which were typed into the view
+    typed: Vec<Pattern>,
+    enabled: bool,
+    /// the patterns while they are typed
+    input: Option<String>,
+    /// why the input can not be parsed
+    error: Option<String>,
+    /// how the ignored entries are shown, see [`Node::ignored`]
+    ignored: IgnoredEntries,
+    /// whether the ignored entries are shown like any other, regardless of `ignored`
+    show_ignored: bool,
+    /// the filter which is applied to the tree
+    active: Filter,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self {
+            configured: Vec::new(),
+            typed: Vec::new(),
+            enabled: true,
+            input: None,
+            error: None,
+            ignored: IgnoredEntries::default(),
+            show_ignored: false,
+            active: Filter::default(),
+        }
+    }
+}
+
+impl Filters {
+    /// The filter of `typed` together with the configured patterns, if the filters are
+    /// enabled.
+    fn filter(&self, typed: &[Pattern]) -> Filter {
+        let filter = if self.enabled {
+            Filter::new(self.configured.iter().chain(typed).cloned())
+        } else {
+            Filter::default()
+        };
+        filter.hide_ignored(self.treats_ignored(IgnoredEntries::Hide))
+    }
+
+    fn treats_ignored(&self, ignored: IgnoredEntries) -> bool {
+        !self.show_ignored && self.ignored == ignored
+    }
+
+    fn patterns(&self) -> impl Iterator<Item = &Pattern> {
+        self.configured.iter().chain(&self.typed)
+    }
+}
+
+/// The preview of the selected entry beside the tree.
+struct PreviewPane {
+    settings: PreviewSettings,
+    show: bool,
+    /// the share of the width of the view in percent
+    width: u16,
+    /// the preview which is shown, which may still be the one of the previous selection
+    content: Option<Preview>,
+    /// the entry whose preview is shown or read right now
+    requested: Option<PathBuf>,
+    token: Option<CancellationToken>,
+}
+
+impl Default for PreviewPane {
+    fn default() -> Self {
+        let settings = PreviewSettings::default();
+        Self {
+            show: settings.show,
+            width: settings.width,
+            settings,
+            content: None,
+            requested: None,
+            token: None,
+        }
+    }
+}
+
+impl PreviewPane {
+    /// Forget the requested entry, so it is read again the next time.
+    fn invalidate(&mut self) {
+        if let Some(token) = self.token.take() {
+            token.cancel();
+        }
+        self.requested = None;
+    }
+
+    fn is_loading(&self) -> bool {
+        self.content.as_ref().map(|content| &content.path) != self.requested.as_ref()
+    }
+}
+
+/// The extraction of an archive or of one of its entries.
+#[derive(Default)]
+struct Extraction {
+    /// the archive and the entry inside of it which is extracted, or the whole archive
+    source: Option<(PathBuf, Option<PathBuf>)>,
+    /// the destination while it is typed
+    input: Option<String>,
+    /// the outcome of the last extraction, which is shown until the selection moves
+    status: Option<String>,
+}
+
+/// The directories whose order is changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortTarget {
+    All,
+    Dir(Handle),
+}
+
 pub struct Home {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    tree: FileTree,
+    /// the currently visible nodes in the order they are rendered
+    rows: Vec<Handle>,
+    /// the selected index into `rows`
+    state: ListState,
+    /// the index of the first row which fits into the view
+    offset: usize,
+    /// the rendered rows of the view, which are only rebuilt if the window moved or is stale
+    window: Vec<ListItem<'static>>,
+    window_rows: std::ops::Range<usize>,
+    window_stale: bool,
+    /// the rendered detail columns of the rows of the window
+    window_columns: Vec<ListItem<'static>>,
+    /// whether anything changed since the last draw
+    needs_render: bool,
+    /// the paths which are picked together, marked by path so they survive reloads
+    marked: HashSet<PathBuf>,
+    /// the directories which are currently loaded in the background
+    loads: HashMap<Handle, Load>,
+    /// the directories whose disk usage is currently measured in the background
+    measures: HashMap<Handle, Load>,
+    next_load_id: u64,
+    /// watches the loaded directories, so their cached children stay up to date even while
+    /// they are collapsed
+    watcher: Option<TreeWatcher>,
+    search: Search,
+    filters: Filters,
+    /// the order from the config, which is restored when the order is reset
+    configured_sort: SortOrder,
+    /// the directories whose order is changed right now
+    sorting: Option<SortTarget>,
+    columns: Columns,
+    show_columns: bool,
+    details: Details,
+    preview: PreviewPane,
+    extraction: Extraction,
+    /// the git status of the entries, if the tree is inside of a repository
+    git: Option<GitStatuses>,
+    /// cancels reading the git status while it is read in the background
+    git_token: Option<CancellationToken>,
+    /// whether the status changed again while it was read
+    git_stale: bool,
 }
 
 impl Home {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(tree: FileTree) -> Self {
+        let rows = tree.flatten();
+
+        Self {
+            command_tx: None,
+            config: Config::default(),
+            tree,
+            rows,
+            state: ListState::default().with_selected(Some(0)),
+            offset: 0,
+            window: Vec::new(),
+            window_rows: 0..0,
+            window_stale: true,
+            window_columns: Vec::new(),
+            needs_render: true,
+            marked: HashSet::new(),
+            loads: HashMap::new(),
+            measures: HashMap::new(),
+            next_load_id: 0,
+            watcher: None,
+            search: Search::default(),
+            filters: Filters::default(),
+            configured_sort: SortOrder::default(),
+            sorting: None,
+            columns: Columns::default(),
+            show_columns: false,
+            details: Details::default(),
+            preview: PreviewPane::default(),
+            extraction: Extraction::default(),
+            git: None,
+            git_token: None,
+            git_stale: false,
+        }
+    }
+
+    fn selected(&self) -> Option<Handle> {
+        self.state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+            .copied()
+    }
+
+    fn select(&mut self, handle: Handle) {
+        if let Some(index) = self.rows.iter().position(|row| *row == handle) {
+            self.select_index(index);
+        }
+    }
+
+    /// Select the row at `index`, or the last one if there are fewer rows.
+    fn select_index(&mut self, index: usize) {
+        let last = self.rows.len().saturating_sub(1);
+        let index = Some(index.min(last));
+        if self.state.selected() != index {
+            self.state.select(index);
+            self.needs_render = true;
+        }
+    }
+
+    fn select_next(&mut self) {
+        let index = self.state.selected().map_or(0, |index| index + 1);
+        self.select_index(index);
+    }
+
+    fn select_prev(&mut self) {
+        let index = self.state.selected().unwrap_or_default();
+        self.select_index(index.saturating_sub(1));
+    }
+
+    /// Recompute the visible rows and keep the selection on the same node if it is still visible.
+    fn update_rows(&mut self) {
+        let selected = self.selected();
+        self.rows = self.tree.flatten_filtered(&self.filters.active);
+        self.window_stale = true;
+        if let Some(handle) = selected {
+            self.select(handle);
+        }
+        // the selected node may have been removed from the end of the tree
+        self.select_index(self.state.selected().unwrap_or_default());
+        self.cancel_hidden_loads();
+    }
+
+    /// Recompute only the rows of `dir` and its descendants, after it was expanded or
+    /// collapsed or its children changed.
+    ///
+    /// An active filter may show or hide the ancestors of `dir` as well, so all rows are
+    /// recomputed then.
+    fn update_rows_below(&mut self, dir: Handle) {
+        if !self.filters.active.is_empty() {
+            self.update_rows();
+            return;
+        }
+        // the children of a directory which is not visible are not visible either
+        let Some(start) = self.rows.iter().position(|row| *row == dir) else {
+            return;
+        };
+        let depth = self.tree.get(dir).map_or(0, |node| node.depth);
+        // removed children are still part of the old rows of the directory
+        let end = self.rows[start + 1..]
+            .iter()
+            .position(|row| self.tree.get(*row).is_some_and(|node| node.depth <= depth))
+            .map_or(self.rows.len(), |len| start + 1 + len);
+
+        let selected = self.selected();
+        self.rows.splice(start..end, self.tree.flatten_from(dir));
+        self.window_stale = true;
+        if let Some(handle) = selected {
+            self.select(handle);
+        }
+        self.select_index(self.state.selected().unwrap_or_default());
+    }
+
+    /// Cancel the loads of the directories which are not visible as open rows anymore, e.g.
+    /// because a filter hides them. A deep search loads hidden directories on purpose.
+    fn cancel_hidden_loads(&mut self) {
+        if self.loads.is_empty() || self.search.deep {
+            return;
+        }
+        let visible: HashSet<Handle> = self.rows.iter().copied().collect();
+        let hidden: Vec<Handle> = self
+            .loads
+            .keys()
+            .copied()
+            .filter(|dir| !visible.contains(dir) || !self.tree.get(*dir).is_some_and(Node::is_open))
+            .collect();
+        for dir in hidden {
+            self.cancel_loads_below(dir);
+        }
+    }
+
+    fn expand(&mut self, handle: Handle) {
+        let Some(node) = self.tree.get(handle) else {
+            return;
+        };
+        let Some(load) = node.load_state() else {
+            return;
+        };
+
+        if load == LoadState::NotLoaded {
+            self.start_loading(handle);
+        }
+        self.tree.set_open(handle, true);
+        self.update_rows_below(handle);
+    }
+
+    /// Read the children of the directory or archive `handle` on the blocking thread pool.
+    ///
+    /// The entries are sent back as [`HomeAction::Loaded`] and added to the tree as they arrive.
+    /// Archives are listed as a whole and sent back as [`HomeAction::ArchiveListed`].
+    fn start_loading(&mut self, handle: Handle) {
+        let Some(node) = self.tree.get(handle) else {
+            return;
+        };
+        let (path, is_archive) = (
+            node.path.clone(),
+            matches!(node.kind, NodeKind::Archive { .. }),
+        );
+        // without an action handler there is nobody to receive the entries
+        let Some(tx) = self.command_tx.clone() else {
+            if let Err(err) = self.tree.load_children(handle) {
+                error!("Failed to read {:?}: {}", path, err);
+            }
+            return;
+        };
+
+        self.next_load_id += 1;
+        let id = LoadId {
+            dir: handle,
+            id: self.next_load_id,
+        };
+        let token = CancellationToken::new();
+        self.loads.insert(
+            handle,
+            Load {
+                id: id.id,
+                token: token.clone(),
+            },
+        );
+        self.tree.clear_children(handle);
+        self.tree.set_load_state(handle, LoadState::Loading);
+
+        if is_archive {
+            list_in_background(path, token, move |result| {
+                let actions = match result {
+                    Ok(entries) => vec![
+                        HomeAction::ArchiveListed(id, entries),
+                        HomeAction::LoadFinished(id, None),
+                    ],
+                    Err(err) => vec![HomeAction::LoadFinished(id, Some(err.to_string()))],
+                };
+                for action in actions {
+                    let _ = tx.send(action.into());
+                }
+            });
+            return;
+        }
+        let ignores = self.tree.ignore_rules().clone();
+        load_in_background(path, ignores, token.clone(), move |event| {
+            let action = match event {
+                LoadEvent::Entries(nodes) => HomeAction::Loaded(id, NodeBatch(nodes)),
+                LoadEvent::Finished(result) => {
+                    HomeAction::LoadFinished(id, result.err().map(|err| err.to_string()))
+                }
+            };
+            if tx.send(action.into()).is_err() {
+                // the app is gone, so the rest of the directory is not needed anymore
+                token.cancel();
+            }
+        });
+    }
+
+    /// Whether `id` belongs to a load that was neither finished nor cancelled yet.
+    fn is_loading(&self, id: LoadId) -> bool {
+        self.loads.get(&id.dir).is_some_and(|load| load.id == id.id)
+    }
+
+    fn add_listed_archive(&mut self, id: LoadId, entries: Vec<ArchivedEntry>) {
+        if !self.is_loading(id) {
+            return;
+        }
+        self.tree.add_archived(id.dir, entries);
+        self.update_rows_below(id.dir);
+        self.needs_render = true;
+    }
+
+    fn add_loaded(&mut self, id: LoadId, batch: NodeBatch) {
+        if !self.is_loading(id) {
+            return;
+        }
+        self.tree.add_children(id.dir, batch.0);
+        self.update_rows_below(id.dir);
+        self.needs_render = true;
+    }
+
+    fn finish_loading(&mut self, id: LoadId, error: Option<String>) {
+        if !self.is_loading(id) {
+            return;
+        }
+        self.loads.remove(&id.dir);
+        self.needs_render = true;
+
+        let path = self.tree.get(id.dir).map(|node| node.path.clone());
+        match error {
+            None => {
+                debug!("Loaded children of {:?}", path);
+                self.tree.set_load_state(id.dir, LoadState::Loaded);
+                // archives are read again when their directory sees them change
+                let is_dir = self.tree.get(id.dir).is_some_and(|node| node.is_dir());
+                if let (Some(watcher), Some(path), true) = (&mut self.watcher, &path, is_dir) {
+                    watcher.watch(path);
+                }
+                if self.search.deep {
+                    self.load_unloaded(id.dir);
+                }
+            }
+            Some(err) => {
+                error!("Failed to read {:?}: {}", path, err);
+                self.tree.set_load_state(id.dir, LoadState::NotLoaded);
+            }
+        }
+        self.update_rows_below(id.dir);
+        if !self.search.query.is_empty() {
+            // follow the matches while the query is typed and nothing matched so far
+            let on_match = self
+                .selected()
+                .is_some_and(|selected| self.search.indices.contains_key(&selected));
+            self.update_matches(self.search.typing && !on_match);
+        }
+    }
+
+    /// Read the loaded directories among `dirs` again, after their entries changed on disk.
+    fn refresh_changed(&mut self, dirs: Vec<PathBuf>) {
+        // the previewed entry or its listing may have changed as well
+        if let Some(requested) = &self.preview.requested
+            && dirs
+                .iter()
+                .any(|dir| dir == requested || requested.parent() == Some(dir.as_path()))
+        {
+            self.preview.invalidate();
+        }
+        // changed entries or a changed index, e.g. after staging or committing. Without a
+        // status the tree is not inside of a repository, unless the first read is in flight
+        let affects_git = match &self.git {
+            Some(git) => dirs.iter().any(|dir| git.is_affected_by(dir)),
+            None => self.git_token.is_some(),
+        };
+        for dir in dirs {
+            if let Some(handle) = self.tree.find(&dir) {
+                self.reread(handle);
+            }
+        }
+        if affects_git {
+            self.read_git_status();
+        }
+    }
+
+    /// Read every loaded directory and the git status again, whether it is open or not.
+    fn refresh_all(&mut self) {
+        for handle in self.tree.loaded_dirs() {
+            self.reread(handle);
+        }
+        self.read_git_status();
+    }
+
+    /// Read the git status of the tree in the background, which is sent back as
+    /// [`HomeAction::GitRead`].
+    ///
+    /// While a read is in flight, only one more read is made once it is finished, so a burst
+    /// of changes does not run a status for every one of them.
+    fn read_git_status(&mut self) {
+        if self.git_token.is_some() {
+            self.git_stale = true;
+            return;
+        }
+        let Some(root) = self
+            .tree
+            .get(self.tree.root())
+            .map(|node| node.path.clone())
+        else {
+            return;
+        };
+        // without an action handler there is nobody to receive the status
+        let Some(tx) = self.command_tx.clone() else {
+            self.set_git_status(GitStatuses::read(&root));
+            return;
+        };
+        let token = CancellationToken::new();
+        self.git_token = Some(token.clone());
+        git::read_in_background(root, token, move |statuses| {
+            let _ = tx.send(HomeAction::GitRead(statuses).into());
+        });
+    }
+
+    fn set_git_status(&mut self, statuses: Option<GitStatuses>) {
+        self.git_token = None;
+        // staging and committing only change the repository, not the entries of the tree
+        if let (Some(watcher), Some(statuses)) = (&mut self.watcher, &statuses) {
+            watcher.watch(&statuses.git_dir);
+        }
+        self.git = statuses;
+        self.window_stale = true;
+        self.needs_render = true;
+        if mem::take(&mut self.git_stale) {
+            self.read_git_status();
+        }
+    }
+
+    /// Read the loaded directory `handle` again and patch the fresh listing into the tree.
+    fn reread(&mut self, handle: Handle) {
+        // directories that are loading right now pick up the changes themselves
+        let Some(node) = self.tree.get(handle) else {
+            return;
+        };
+        let NodeKind::Directory {
+            load: LoadState::Loaded,
+            ..
+        } = node.kind
+        else {
+            return;
+        };
+        let dir = node.path.clone();
+
+        let Some(tx) = self.command_tx.clone() else {
+            match read_entries(&dir, self.tree.ignore_rules()) {
+                Ok(nodes) => self.apply_listing(handle, NodeBatch(nodes)),
+                Err(err) => debug!("Failed to read {:?} again: {}", dir, err),
+            }
+            return;
+        };
+        let ignores = self.tree.ignore_rules().clone();
+        read_in_background(dir.clone(), ignores, move |result| match result {
+            Ok(nodes) => {
+                let _ = tx.send(HomeAction::Listed(handle, NodeBatch(nodes)).into());
+            }
+            // the directory is removed by the refresh of its parent if it was deleted
+            Err(err) => debug!("Failed to read {:?} again: {}", dir, err),
+        });
+    }
+
+    /// Patch the children of `handle` with a fresh listing of the directory.
+    ///
+    /// If the selected node vanished, its nearest surviving ancestor is selected instead.
+    fn apply_listing(&mut self, handle: Handle, batch: NodeBatch) {
+        let Some(NodeKind::Directory {
+            load: LoadState::Loaded,
+            ..
+        }) = self.tree.get(handle).map(|node| &node.kind)
+        else {
+            return;
+        };
+        let ancestors = self
+            .selected()
+            .map(|selected| self.ancestors(selected))
+            .unwrap_or_default();
+
+        let removed = self.tree.patch_children(handle, batch.0);
+        self.needs_render = true;
+        if let Some(watcher) = &mut self.watcher {
+            for path in removed {
+                watcher.unwatch(&path);
+            }
+        }
+
+        self.update_rows();
+        if !self.search.query.is_empty() {
+            self.update_matches(false);
+        }
+        if let Some(survivor) = ancestors
+            .into_iter()
+            .find(|ancestor| self.tree.get(*ancestor).is_some())
+        {
+            self.select(survivor);
+        }
+    }
+
+    /// `handle` itself followed by its parent, grandparent and so on up to the root.
+    fn ancestors(&self, handle: Handle) -> Vec<Handle> {
+        let mut ancestors = Vec::new();
+        let mut current = Some(handle);
+        while let Some(handle) = current {
+            ancestors.push(handle);
+            current = self.tree.get(handle).and_then(|node| node.parent);
+        }
+        ancestors
+    }
+
+    /// Measure the disk usage of the directories `dirs` on the blocking thread pool.
+    ///
+    /// The running totals are sent back as [`HomeAction::Measuring`] and the final one as
+    /// [`HomeAction::Measured`]. Directories which are measured already are not measured again
+    /// until they are finished.
+    fn start_measuring(&mut self, dirs: Vec<Handle>) {
+        // without an action handler there is nobody to receive the usage
+        let Some(tx) = self.command_tx.clone() else {
+            return;
+        };
+
+        for handle in dirs {
+            let Some(node) = self.tree.get(handle) else {
+                continue;
+            };
+            // the entries of archives take no space of their own
+            if !matches!(node.kind, NodeKind::Directory { .. })
+                || self.measures.contains_key(&handle)
+            {
+                continue;
+            }
+            let path = node.path.clone();
+
+            self.next_load_id += 1;
+            let id = LoadId {
+                dir: handle,
+                id: self.next_load_id,
+            };
+            let token = CancellationToken::new();
+            self.measures.insert(
+                handle,
+                Load {
+                    id: id.id,
+                    token: token.clone(),
+                },
+            );
+
+            let tx = tx.clone();
+            measure_in_background(path, token.clone(), move |event| {
+                let action = match event {
+                    UsageEvent::Progress(usage) => HomeAction::Measuring(id, usage),
+                    UsageEvent::Finished(usage) => HomeAction::Measured(id, usage),
+                };
+                if tx.send(action.into()).is_err() {
+                    token.cancel();
+                }
+            });
+        }
+        self.window_stale = true;
+    }
+
+    /// Whether `id` belongs to a measurement that was not finished yet.
+    fn is_measuring(&self, id: LoadId) -> bool {
+        self.measures
+            .get(&id.dir)
+            .is_some_and(|measure| measure.id == id.id)
+    }
+
+    fn add_measured(&mut self, id: LoadId, usage: DiskUsage, finished: bool) {
+        if !self.is_measuring(id) {
+            return;
+        }
+        self.needs_render = true;
+        if finished {
+            self.measures.remove(&id.dir);
+            // only the final usage moves the directory, so it does not jump around meanwhile
+            self.tree.set_usage(id.dir, usage);
+            self.update_rows();
+        } else if let Some(node) = self.tree.get_mut(id.dir) {
+            node.usage = Some(usage);
+            self.window_stale = true;
+        }
+    }
+
+    /// Read the preview of the selected entry if it is shown and not read yet.
+    ///
+    /// The preview is read in the background and sent back as [`HomeAction::Previewed`], so
+    /// the previous one stays in place until it arrives.
+    fn sync_preview(&mut self) {
+        if !self.preview.show {
+            return;
+        }
+        let Some(node) = self.selected().and_then(|handle| self.tree.get(handle)) else {
+            return;
+        };
+        if self.preview.requested.as_ref() == Some(&node.path) {
+            return;
+        }
+        let node = node.clone();
+        self.preview.invalidate();
+        self.preview.requested = Some(node.path.clone());
+        self.needs_render = true;
+
+        // without an action handler there is nobody to receive the preview
+        let Some(tx) = self.command_tx.clone() else {
+            self.preview.content = Some(read_preview(&node, &self.preview.settings));
+            return;
+        };
+        let token = CancellationToken::new();
+        self.preview.token = Some(token.clone());
+        preview_in_background(node, self.preview.settings.clone(), token, move |preview| {
+            let _ = tx.send(HomeAction::Previewed(preview).into());
+        });
+    }
+
+    /// Start typing the destination of the selected archive or archived entry, which begins
+    /// with the directory of the archive.
+    fn start_extract(&mut self) -> Option<Action> {
+        let node = self.tree.get(self.selected()?)?;
+        let source = match (&node.kind, node.archived_entry()) {
+            (NodeKind::Archive { .. }, _) => (node.path.clone(), None),
+            (_, Some(entry)) => (entry.archive.clone(), Some(entry.inner.clone())),
+            _ => return None,
+        };
+        let dir = source.0.parent().unwrap_or(Path::new(""));
+        self.extraction.input = Some(dir.display().to_string());
+        self.extraction.source = Some(source);
+        self.extraction.status = None;
+        Some(AppAction::SetMode(Mode::Extract).into())
+    }
+
+    /// Stop typing the destination and extract to it if `confirm` is set.
+    ///
+    /// A relative destination is taken relative to the directory of the archive.
+    fn stop_extract(&mut self, confirm: bool) -> Option<Action> {
+        let input = self.extraction.input.take()?;
+        let (archive, inner) = self.extraction.source.take()?;
+        if confirm && !input.trim().is_empty() {
+            let dir = archive.parent().unwrap_or(Path::new(""));
+            let dest = dir.join(input.trim());
+            match self.command_tx.clone() {
+                Some(tx) => {
+                    self.extraction.status = Some(format!("extracting to {}…", dest.display()));
+                    extract_in_background(archive, inner, dest.clone(), move |result| {
+                        let result = result.map_err(|err| err.to_string());
+                        let _ = tx.send(HomeAction::Extracted(dest, result).into());
+                    });
+                }
+                None => {
+                    let result = extract(&archive, inner.as_deref(), &dest);
+                    self.finish_extract(dest, result.map_err(|err| err.to_string()));
+                }
+            }
+        }
+        Some(AppAction::SetMode(Mode::Home).into())
+    }
+
+    fn finish_extract(&mut self, dest: PathBuf, result: Result<usize, String>) {
+        self.extraction.status = Some(match result {
+            Ok(1) => format!("extracted 1 entry to {}", dest.display()),
+            Ok(count) => format!("extracted {count} entries to {}", dest.display()),
+            Err(err) => format!("failed to extract: {err}"),
+        });
+    }
+
+    /// The destination while it is typed or the outcome of the last extraction.
+    fn extract_status(&self) -> Option<String> {
+        match (&self.extraction.input, &self.extraction.status) {
+            (Some(input), _) => Some(format!(" extract to: {input}▏ ")),
+            (None, Some(status)) => Some(format!(" {status} ")),
+            (None, None) => None,
+        }
+    }
+
+    fn resize_preview(&mut self, step: i16) {
+        let width = self.preview.width.saturating_add_signed(step);
+        self.preview.width = width.clamp(*PREVIEW_WIDTHS.start(), *PREVIEW_WIDTHS.end());
+        self.window_stale = true;
+    }
+
+    /// Cancel the loads of `handle` and of all directories below it.
+    ///
+    /// The partially loaded directories are reset, so they are loaded again when they are
+    /// expanded the next time.
+    fn cancel_loads_below(&mut self, handle: Handle) {
+        let cancelled: Vec<Handle> = self
+            .loads
+            .keys()
+            .copied()
+            .filter(|dir| self.is_below(*dir, handle))
+            .collect();
+
+        for dir in cancelled {
+            if let Some(load) = self.loads.remove(&dir) {
+                load.token.cancel();
+            }
+            self.tree.clear_children(dir);
+            self.tree.set_load_state(dir, LoadState::NotLoaded);
+            debug!(
+                "Cancelled loading {:?}",
+                self.tree.get(dir).map(|node| &node.path)
+            );
+        }
+    }
+
+    /// Whether `handle` is `ancestor` itself or one of its descendants.
+    fn is_below(&self, handle: Handle, ancestor: Handle) -> bool {
+        self.ancestors(handle).contains(&ancestor)
+    }
+
+    /// Close the selected directory or, if it is not an open directory, close its parent
+    /// and move the selection there.
+    fn collapse(&mut self, handle: Handle) {
+        let Some(node) = self.tree.get(handle) else {
+            return;
+        };
+
+        if node.is_open() {
+            self.cancel_loads_below(handle);
+            self.tree.set_open(handle, false);
+            self.update_rows_below(handle);
+        } else if let Some(parent) = node.parent {
+            self.cancel_loads_below(parent);
+            self.tree.set_open(parent, false);
+            self.update_rows_below(parent);
+            self.select(parent);
+        }
+    }
+
+    fn toggle(&mut self, handle: Handle) {
+        match self.tree.get(handle) {
+            Some(node) if node.is_open() => self.collapse(handle),
+            Some(node) if node.is_expandable() => self.expand(handle),
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, handle: Handle) -> Option<Action> {
+        let node = self.tree.get(handle)?;
+        if node.is_dir() {
+            self.toggle(handle);
+            None
+        } else if node.archived_entry().is_some() {
+            // the entries of archives are not on disk, so they can only be extracted
+            None
+        } else {
+            self.pick()
+        }
+    }
+
+    /// The selected directory or the directory which contains the selected file.
+    fn selected_dir(&self) -> Option<PathBuf> {
+        let node = self.tree.get(self.selected()?)?;
+        if let Some(entry) = node.archived_entry() {
+            entry.archive.parent().map(Path::to_path_buf)
+        } else if node.is_dir() {
+            Some(node.path.clone())
+        } else {
+            let parent = self.tree.get(node.parent?)?;
+            Some(parent.path.clone())
+        }
+    }
+
+    fn toggle_mark(&mut self, handle: Handle) {
+        let Some(node) = self.tree.get(handle) else {
+            return;
+        };
+        if node.archived_entry().is_some() {
+            return;
+        }
+        if !self.marked.remove(&node.path) {
+            self.marked.insert(node.path.clone());
+        }
+        self.window_stale = true;
+        self.select_next();
+    }
+
+    fn pick(&self) -> Option<Action> {
+        let paths = if self.marked.is_empty() {
+            let node = self.tree.get(self.selected()?)?;
+            if node.archived_entry().is_some() {
+                return None;
+            }
+            vec![node.path.clone()]
+        } else {
+            let mut paths: Vec<PathBuf> = self.marked.iter().cloned().collect();
+            paths.sort();
+            paths
+        };
+        Some(AppAction::Pick(paths).into())
+    }
+
+    fn start_search(&mut self) -> Option<Action> {
+        self.search = Search {
+            typing: true,
+            origin: self.selected(),
+            ..Search::default()
+        };
+        self.window_stale = true;
+        Some(AppAction::SetMode(Mode::Search).into())
+    }
+
+    fn edit_query(&mut self, edit: impl FnOnce(&mut String)) {
+        if !self.search.typing {
+            return;
+        }
+        edit(&mut self.search.query);
+        self.update_matches(true);
+    }
+
+    /// Match the query against the loaded nodes again and, if `jump` is set, select the
+    /// best match.
+    fn update_matches(&mut self, jump: bool) {
+        self.search.matches = find_matches(&self.tree, &self.search.query);
+        self.search.indices = self
+            .search
+            .matches
+            .iter()
+            .map(|m| (m.handle, m.indices.clone()))
+            .collect();
+        self.window_stale = true;
+
+        if jump
+            && let Some(best) = self
+                .search
+                .matches
+                .iter()
+                .max_by_key(|m| m.score)
+                .map(|m| m.handle)
+        {
+            self.reveal(best);
+        }
+    }
+
+    /// Load every directory below `handle` which is not loaded yet, so it can be searched.
+    fn load_unloaded(&mut self, handle: Handle) {
+        let unloaded: Vec<Handle> = self
+            .tree
+            .children(handle)
+            .filter(|child| {
+                self.tree.get(*child).is_some_and(|node| {
+                    // following symlinks could lead into a loop
+                    node.symlink_target.is_none()
+                        && node.kind
+                            == (NodeKind::Directory {
+                                is_open: false,
+                                load: LoadState::NotLoaded,
+                            })
+                })
+            })
+            .collect();
+        for dir in unloaded {
+            self.start_loading(dir);
+        }
+
+        let loaded: Vec<Handle> = self
+            .tree
+            .children(handle)
+            .filter(|child| {
+                matches!(
+                    self.tree.get(*child).map(|node| &node.kind),
+                    Some(NodeKind::Directory {
+                        load: LoadState::Loaded,
+                        ..
+                    })
+                )
+            })
+            .collect();
+        for dir in loaded {
+            self.load_unloaded(dir);
+        }
+    }
+
+    fn search_all(&mut self) {
+        if self.search.deep {
+            return;
+        }
+        self.search.deep = true;
+        self.load_unloaded(self.tree.root());
+        self.update_rows();
+        // directories which are loaded in the background are matched when they are finished
+        self.update_matches(true);
+    }
+
+    fn stop_search(&mut self, confirm: bool) -> Option<Action> {
+        let was_typing = self.search.typing;
+        // the directories which were only loaded for the search are cancelled with the rows
+        if confirm {
+            self.search.typing = false;
+            self.search.deep = false;
+        } else {
+            if was_typing && let Some(origin) = self.search.origin {
+                self.reveal(origin);
+            }
+            self.search = Search::default();
+        }
+        self.update_rows();
+        was_typing.then(|| AppAction::SetMode(Mode::Home).into())
+    }
+
+    /// Select the next match after the selection in the order of the tree, or the previous
+    /// one if `forward` is not set. The search wraps around at the end of the tree.
+    fn select_match(&mut self, forward: bool) {
+        if self.search.matches.is_empty() {
+            return;
+        }
+        let order: HashMap<Handle, usize> = self
+            .tree
+            .walk()
+            .into_iter()
+            .enumerate()
+            .map(|(index, handle)| (handle, index))
+            .collect();
+        let position = |handle: Handle| order.get(&handle).copied().unwrap_or_default();
+        let current = self.selected().map(position).unwrap_or_default();
+
+        let matches = &self.search.matches;
+        let next = if forward {
+            matches
+                .iter()
+                .find(|m| position(m.handle) > current)
+                .or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|m| position(m.handle) < current)
+                .or(matches.last())
+        };
+        if let Some(next) = next.map(|m| m.handle) {
+            self.reveal(next);
+        }
+    }
+
+    /// Open all ancestors of `handle` and select it.
+    fn reveal(&mut self, handle: Handle) {
+        for ancestor in self.ancestors(handle).into_iter().skip(1) {
+            self.tree.set_open(ancestor, true);
+        }
+        self.update_rows();
+        self.select(handle);
+    }
+
+    fn start_filter(&mut self) -> Option<Action> {
+        let patterns: Vec<String> = self.filters.typed.iter().map(ToString::to_string).collect();
+        self.filters.input = Some(patterns.join(" "));
+        self.filters.error = None;
+        self.window_stale = true;
+        Some(AppAction::SetMode(Mode::Filter).into())
+    }
+
+    /// Change the typed patterns and filter the tree with them right away if they are valid.
+    fn edit_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        let Some(input) = &mut self.filters.input else {
+            return;
+        };
+        edit(input);
+        match Pattern::parse_all(input) {
+            Ok(typed) => {
+                self.filters.error = None;
+                self.filters.active = self.filters.filter(&typed);
+                self.update_rows();
+            }
+            Err(err) => self.filters.error = Some(err),
+        }
+    }
+
+    /// Stop typing the filter patterns and keep them if `confirm` is set and they are valid.
+    fn stop_filter(&mut self, confirm: bool) -> Option<Action> {
+        let input = self.filters.input.take()?;
+        if confirm {
+            match Pattern::parse_all(&input) {
+                Ok(typed) => self.filters.typed = typed,
+                Err(err) => {
+                    // keep typing, so the patterns can be corrected
+                    self.filters.input = Some(input);
+                    self.filters.error = Some(err);
+                    return None;
+                }
+            }
+            // typing new patterns turns the filters on again
+            self.filters.enabled = true;
+        }
+        self.filters.error = None;
+        self.filters.active = self.filters.filter(&self.filters.typed);
+        self.update_rows();
+        Some(AppAction::SetMode(Mode::Home).into())
+    }
+
+    fn toggle_ignored(&mut self) {
+        self.filters.show_ignored = !self.filters.show_ignored;
+        self.filters.active = self.filters.filter(&self.filters.typed);
+        self.update_rows();
+    }
+
+    fn toggle_filters(&mut self) {
+        self.filters.enabled = !self.filters.enabled;
+        self.filters.active = self.filters.filter(&self.filters.typed);
+        self.update_rows();
+    }
+
+    /// The status of the filters which is shown at the bottom of the view.
+    fn filter_status(&self) -> Option<String> {
+        if let Some(input) = &self.filters.input {
+            let error = self
+                .filters
+                .error
+                .as_ref()
+                .map(|err| format!(" ({})", err.lines().last().unwrap_or_default()))
+                .unwrap_or_default();
+            return Some(format!(" filter: {input}▏{error} "));
+        }
+        let patterns: Vec<String> = self.filters.patterns().map(ToString::to_string).collect();
+        if patterns.is_empty() {
+            None
+        } else if self.filters.enabled {
+            Some(format!(" filter: {} ", patterns.join(" ")))
+        } else {
+            Some(" filters off ".to_string())
+        }
+    }
+
+    fn start_sort(&mut self, target: SortTarget) -> Option<Action> {
+        self.sorting = Some(target);
+        Some(AppAction::SetMode(Mode::Sort).into())
+    }
+
+    /// The directory whose entries are around the selection.
+    fn selected_parent(&self) -> Handle {
+        self.selected()
+            .and_then(|selected| self.tree.get(selected)?.parent)
+            .unwrap_or(self.tree.root())
+    }
+
+    /// Change the order of the directories which are sorted right now.
+    fn edit_sort(&mut self, edit: impl FnOnce(&mut SortOrder)) {
+        match self.sorting {
+            Some(SortTarget::All) => {
+                let mut order = self.tree.global_sort_order().clone();
+                edit(&mut order);
+                self.tree.set_sort_order(order);
+            }
+            Some(SortTarget::Dir(dir)) => {
+                let mut order = self.tree.sort_order(dir).clone();
+                edit(&mut order);
+                self.tree.set_dir_sort_order(dir, Some(order));
+            }
+            None => return,
+        }
+        self.update_rows();
+    }
+
+    fn reset_sort(&mut self) {
+        match self.sorting {
+            Some(SortTarget::All) => self.tree.set_sort_order(self.configured_sort.clone()),
+            Some(SortTarget::Dir(dir)) => self.tree.set_dir_sort_order(dir, None),
+            None => return,
+        }
+        self.update_rows();
+    }
+
+    fn stop_sort(&mut self) -> Option<Action> {
+        let target = self.sorting.take()?;
+        self.save_sort(target);
+        Some(AppAction::SetMode(Mode::Home).into())
+    }
+
+    /// Save the order of `target` to the config directory, so it is used from now on.
+    fn save_sort(&self, target: SortTarget) {
+        let Some(config_dir) = &self.config.config_dir else {
+            return;
+        };
+        let result = match target {
+            SortTarget::All => save_global_sort(config_dir, self.tree.global_sort_order()),
+            SortTarget::Dir(dir) => {
+                let Some(node) = self.tree.get(dir) else {
+                    return;
+                };
+                let order = self
+                    .tree
+                    .has_own_sort_order(dir)
+                    .then(|| self.tree.sort_order(dir));
+                save_dir_sort(config_dir, &node.path, order)
+            }
+        };
+        if let Err(err) = result {
+            error!("Failed to save the sort order: {}", err);
+        }
+    }
+
+    /// The order which is changed right now, shown at the bottom of the view.
+    fn sort_status(&self) -> Option<String> {
+        match self.sorting? {
+            SortTarget::All => Some(format!(" sort: {} ", self.tree.global_sort_order())),
+            SortTarget::Dir(dir) => {
+                let node = self.tree.get(dir)?;
+                Some(format!(
+                    " sort {}: {} ",
+                    node.name(),
+                    self.tree.sort_order(dir)
+                ))
+            }
+        }
+    }
+
+    /// Scroll the view of `height` rows so the selection is visible and render the rows
+    /// which are inside of it.
+    ///
+    /// Only the visible rows are rendered, so the cost of a frame does not depend on the
+    /// size of the tree.
+    fn update_window(&mut self, height: usize) {
+        let selected = self.state.selected().unwrap_or_default();
+        if selected < self.offset {
+            self.offset = selected;
+        } else if selected >= self.offset + height {
+            self.offset = selected + 1 - height;
+        }
+        // do not leave empty space at the end after rows were removed
+        self.offset = self.offset.min(self.rows.len().saturating_sub(height));
+
+        let rows = self.offset..(self.offset + height).min(self.rows.len());
+        if !self.window_stale && rows == self.window_rows {
+            return;
+        }
+        self.window = self.rows[rows.clone()]
+            .iter()
+            .map(|handle| {
+                self.render_row(*handle)
+                    .unwrap_or_else(|| ListItem::new(""))
+            })
+            .collect();
+        self.window_columns = if self.show_columns {
+            self.rows[rows.clone()]
+                .iter()
+                .map(|handle| self.render_columns(*handle))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.window_rows = rows;
+        self.window_stale = false;
+    }
+
+    fn style(&self, key: &str, default: Style) -> Style {
+        self.config
+            .styles
+            .get(&Mode::Home)
+            .and_then(|styles| styles.get(key))
+            .copied()
+            .unwrap_or(default)
+    }
+
+    /// The width of all shown columns, including the space in front of each of them.
+    fn columns_width(&self) -> u16 {
+        if !self.show_columns {
+            return 0;
+        }
+        self.columns
+            .order
+            .iter()
+            .map(|column| self.columns.width(*column) + 1)
+            .sum()
+    }
+
+    fn render_columns(&self, handle: Handle) -> ListItem<'static> {
+        let Some(node) = self.tree.get(handle) else {
+            return ListItem::new("");
+        };
+        let spans: Vec<Span> = self
+            .columns
+            .order
+            .iter()
+            .map(|column| {
+                let width = self.columns.width(*column) as usize;
+                let value: String = self
+                    .details
+                    .format(*column, node)
+                    .chars()
+                    .take(width)
+                    .collect();
+                let style = self.style(&column.to_string(), column_style(*column));
+                Span::styled(format!(" {value:>width$}"), style)
+            })
+            .collect();
+        ListItem::new(Line::from(spans))
+    }
+
+    fn render_row(&self, handle: Handle) -> Option<ListItem<'static>> {
+        let node = self.tree.get(handle)?;
+        let indent = "  ".repeat(node.depth);
+
+        let prefix = match node.kind {
+            NodeKind::Archive { .. } => "\u{f1c6} ",
+            _ if node.is_dir() && node.is_open() => "\u{f07c} ",
+            _ if node.is_dir() => "\u{f07b} ",
+            _ => "\u{f15b} ",
+        };
+
+        let mut name_style = if self.marked.contains(&node.path) {
+            self.style("marked", Style::new().fg(Color::Yellow))
+        } else {
+            Style::new()
+        };
+        if node.ignored && self.filters.treats_ignored(IgnoredEntries::Dim) {
+            name_style = name_style.patch(self.style("ignored", Style::new().fg(Color::DarkGray)));
+        }
+
+        let mut spans = vec![
+            Span::raw(indent),
+            Span::styled(
+                prefix,
+                self.style("directory", Style::new().fg(Color::Blue)),
+            ),
+        ];
+        match self.search.indices.get(&handle) {
+            Some(indices) => {
+                let match_style = name_style.patch(self.style(
+                    "match",
+                    Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                ));
+                spans.extend(node.name().chars().enumerate().map(|(index, c)| {
+                    let style = if indices.contains(&index) {
+                        match_style
+                    } else {
+                        name_style
+                    };
+                    Span::styled(c.to_string(), style)
+                }));
+            }
+            None => spans.push(Span::styled(node.name().into_owned(), name_style)),
+        }
+        if let Some(status) = self.git.as_ref().and_then(|git| git.get(&node.path))
+            && node.archived_entry().is_none()
+        {
+            let (marker, style) = git_marker(status);
+            spans.push(Span::styled(
+                format!(" {marker}"),
+                self.style(&format!("git_{status}"), style),
+            ));
+        }
+        if let NodeKind::Directory {
+            load: LoadState::Loading,
+            ..
+        } = node.kind
+        {
+            spans.push(Span::styled(
+                " loading…",
+                self.style("loading", Style::new().dim()),
+            ));
+        }
+        if let Some(usage) = node.usage {
+            let measuring = if self.measures.contains_key(&handle) {
+                "…"
+            } else {
+                ""
+            };
+            let files = if usage.files == 1 { "file" } else { "files" };
+            spans.push(Span::styled(
+                format!(
+                    " {}{measuring} in {} {files}",
+                    human_size(usage.bytes),
+                    usage.files
+                ),
+                self.style("usage", Style::new().dim()),
+            ));
+        } else if self.measures.contains_key(&handle) {
+            spans.push(Span::styled(
+                " measuring…",
+                self.style("usage", Style::new().dim()),
+            ));
+        }
+        let line = Line::from(spans);
+        Some(ListItem::new(line))
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) {
+        let title = self
+            .preview
+            .requested
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| format!(" {} ", name.to_string_lossy()))
+            .unwrap_or_default();
+        let status = if self.preview.is_loading() {
+            " loading… ".to_string()
+        } else {
+            self.preview
+                .content
+                .as_ref()
+                .map(|content| format!(" {} ", content.summary))
+                .unwrap_or_default()
+        };
+        let block = Block::bordered()
+            .title(title.bold())
+            .title_bottom(Line::from(status).right_aligned());
+        let lines = self
+            .preview
+            .content
+            .as_ref()
+            .map(|content| content.lines.clone())
+            .unwrap_or_default();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn dispatch(&mut self, home_action: HomeAction) -> Result<Option<Action>> {
+        // the selection and the results of background work, which may be stale, mark the view
+        // dirty themselves once they change anything
+        match home_action {
+            HomeAction::SelectNext
+            | HomeAction::SelectPrev
+            | HomeAction::SelectFirst
+            | HomeAction::SelectLast => {
+                if self.extraction.status.take().is_some() {
+                    self.needs_render = true;
+                }
+            }
+            HomeAction::Loaded(..)
+            | HomeAction::ArchiveListed(..)
+            | HomeAction::LoadFinished(..)
+            | HomeAction::DirsChanged(..)
+            | HomeAction::Listed(..)
+            | HomeAction::Measuring(..)
+            | HomeAction::Measured(..)
+            | HomeAction::GitRead(..)
+            | HomeAction::Previewed(..) => {}
+            _ => self.needs_render = true,
+        }
+        match home_action {
+            HomeAction::SelectNext => self.select_next(),
+            HomeAction::SelectPrev => self.select_prev(),
+            HomeAction::SelectFirst => self.select_index(0),
+            HomeAction::SelectLast => self.select_index(usize::MAX),
+            HomeAction::Pick => return Ok(self.pick()),
+            HomeAction::Refresh => self.refresh_all(),
+            HomeAction::StartSearch => return Ok(self.start_search()),
+            HomeAction::SearchInput(c) => self.edit_query(|query| query.push(c)),
+            HomeAction::SearchBackspace => self.edit_query(|query| {
+                query.pop();
+            }),
+            HomeAction::SearchAll => self.search_all(),
+            HomeAction::ConfirmSearch => return Ok(self.stop_search(true)),
+            HomeAction::CancelSearch => return Ok(self.stop_search(false)),
+            HomeAction::NextMatch => self.select_match(true),
+            HomeAction::PrevMatch => self.select_match(false),
+            HomeAction::StartFilter => return Ok(self.start_filter()),
+            HomeAction::FilterInput(c) => self.edit_filter(|input| input.push(c)),
+            HomeAction::FilterBackspace => self.edit_filter(|input| {
+                input.pop();
+            }),
+            HomeAction::ConfirmFilter => return Ok(self.stop_filter(true)),
+            HomeAction::CancelFilter => return Ok(self.stop_filter(false)),
+            HomeAction::ToggleFilters => self.toggle_filters(),
+            HomeAction::ToggleIgnored => self.toggle_ignored(),
+            HomeAction::StartSort => return Ok(self.start_sort(SortTarget::All)),
+            HomeAction::StartDirSort => {
+                let dir = self.selected_parent();
+                return Ok(self.start_sort(SortTarget::Dir(dir)));
+            }
+            HomeAction::SortByName => self.edit_sort(|order| order.prefer(SortKey::Name)),
+            HomeAction::SortBySize => self.edit_sort(|order| order.prefer(SortKey::Size)),
+            HomeAction::SortByMtime => self.edit_sort(|order| order.prefer(SortKey::Mtime)),
+            HomeAction::SortByExtension => self.edit_sort(|order| order.prefer(SortKey::Extension)),
+            HomeAction::SortByKind => self.edit_sort(|order| order.prefer(SortKey::Kind)),
+            HomeAction::ReverseSort => self.edit_sort(SortOrder::reverse),
+            HomeAction::ToggleDirsFirst => {
+                self.edit_sort(|order| order.dirs_first = !order.dirs_first)
+            }
+            HomeAction::ToggleSortCase => {
+                self.edit_sort(|order| order.ignore_case = !order.ignore_case)
+            }
+            HomeAction::ResetSort => self.reset_sort(),
+            HomeAction::StopSort => return Ok(self.stop_sort()),
+            HomeAction::ToggleColumns => {
+                self.show_columns = !self.show_columns;
+                self.window_stale = true;
+            }
+            HomeAction::MeasureSize => {
+                let selected = self.selected().into_iter().collect();
+                self.start_measuring(selected);
+            }
+            HomeAction::MeasureAll => self.start_measuring(self.rows.clone()),
+            HomeAction::StartExtract => return Ok(self.start_extract()),
+            HomeAction::ExtractInput(c) => {
+                if let Some(input) = &mut self.extraction.input {
+                    input.push(c);
+                }
+            }
+            HomeAction::ExtractBackspace => {
+                if let Some(input) = &mut self.extraction.input {
+                    input.pop();
+                }
+            }
+            HomeAction::ConfirmExtract => return Ok(self.stop_extract(true)),
+            HomeAction::CancelExtract => return Ok(self.stop_extract(false)),
+            HomeAction::Extracted(dest, result) => self.finish_extract(dest, result),
+            HomeAction::TogglePreview => self.preview.show = !self.preview.show,
+            HomeAction::GrowPreview => self.resize_preview(5),
+            HomeAction::ShrinkPreview => self.resize_preview(-5),
+            HomeAction::Loaded(id, batch) => self.add_loaded(id, batch),
+            HomeAction::ArchiveListed(id, entries) => self.add_listed_archive(id, entries),
+            HomeAction::LoadFinished(id, error) => self.finish_loading(id, error),
+            HomeAction::DirsChanged(dirs) => self.refresh_changed(dirs),
+            HomeAction::Listed(handle, batch) => self.apply_listing(handle, batch),
+            HomeAction::Measuring(id, usage) => self.add_measured(id, usage, false),
+            HomeAction::Measured(id, usage) => self.add_measured(id, usage, true),
+            HomeAction::GitRead(statuses) => self.set_git_status(statuses),
+            HomeAction::Previewed(preview) => {
+                if self.preview.requested.as_ref() == Some(&preview.path) {
+                    self.preview.content = Some(preview);
+                    self.needs_render = true;
+                }
+            }
+            _ => {
+                let Some(handle) = self.selected() else {
+                    return Ok(None);
+                };
+                match home_action {
+                    HomeAction::Expand => self.expand(handle),
+                    HomeAction::Collapse => self.collapse(handle),
+                    HomeAction::Toggle => self.toggle(handle),
+                    HomeAction::Open => return Ok(self.open(handle)),
+                    HomeAction::ToggleMark => self.toggle_mark(handle),
+                    _ => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The style of a column if none is configured.
+fn column_style(column: Column) -> Style {
+    match column {
+        Column::Size => Style::new().fg(Color::Green),
+        Column::Mtime => Style::new().fg(Color::Blue),
+        Column::Permissions => Style::new().fg(Color::Yellow),
+        Column::Owner | Column::Group => Style::new().fg(Color::Magenta),
+        Column::Links => Style::new().fg(Color::DarkGray),
+    }
+}
+
+/// The marker beside the name of an entry with the git `status`, and its style if none is
+/// configured as `git_<status>`, e.g. `git_modified`.
+fn git_marker(status: GitStatus) -> (&'static str, Style) {
+    match status {
+        GitStatus::Ignored => ("◌", Style::new().fg(Color::DarkGray)),
+        GitStatus::Untracked => ("?", Style::new().fg(Color::Cyan)),
+        GitStatus::Staged => ("+", Style::new().fg(Color::Green)),
+        GitStatus::Modified => ("~", Style::new().fg(Color::Yellow)),
+        GitStatus::Conflicted => (
+            "!",
+            Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        // the blocking tasks would otherwise keep the runtime from shutting down
+        for load in self.loads.values().chain(self.measures.values()) {
+            load.token.cancel();
+        }
+        self.preview.invalidate();
+        if let Some(token) = &self.git_token {
+            token.cancel();
+        }
     }
 }
 
 impl Component for Home {
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        let watcher_tx = tx.clone();
+        self.watcher = TreeWatcher::new(move |dirs| {
+            let _ = watcher_tx.send(HomeAction::DirsChanged(dirs).into());
+        })
+        .inspect_err(|err| warn!("Changes of the file tree are not watched: {}", err))
+        .ok();
+
+        // only the root is loaded before the first directory is expanded
+        if let (Some(watcher), Some(root)) = (&mut self.watcher, self.tree.get(self.tree.root())) {
+            watcher.watch(&root.path);
+        }
+
         self.command_tx = Some(tx);
+        self.read_git_status();
         Ok(())
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.filters.configured = config.filters.clone();
+        self.filters.ignored = config.ignored;
+        self.configured_sort = config.sort.clone();
+        if self.columns != config.columns {
+            self.show_columns = config.columns.show;
+            self.columns = config.columns.clone();
+        }
+        if self.preview.settings != config.preview {
+            self.preview.show = config.preview.show;
+            self.preview.width = config.preview.width;
+            self.preview.settings = config.preview.clone();
+            self.preview.invalidate();
+        }
+        self.tree.set_sort_order(config.sort.clone());
+        self.tree.set_dir_sort_orders(config.dir_sorts.clone());
+        self.filters.active = self.filters.filter(&self.filters.typed);
+        self.update_rows();
         self.config = config;
+        self.window_stale = true;
+        self.needs_render = true;
         Ok(())
     }
 
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
-        if let Action::Home(home_action) = action {
-            match home_action {
-                HomeAction::None => {}
+        let home_action = match action {
+            Action::Home(home_action) => home_action,
+            // report where the user left off, e.g. for the shell integration
+            Action::App(AppAction::Quit) => {
+                return Ok(self
+                    .selected_dir()
+                    .map(|dir| AppAction::ChangeDir(dir).into()));
             }
+            _ => return Ok(None),
+        };
+        let reply = self.dispatch(home_action)?;
+        // the selection may have moved on
+        self.sync_preview();
+        Ok(reply)
+    }
+
+    /// Type the characters of the search query or the filter patterns while they are typed.
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if !(key.modifiers - KeyModifiers::SHIFT).is_empty() {
+            return Ok(None);
+        }
+        let KeyCode::Char(c) = key.code else {
+            return Ok(None);
+        };
+        if self.extraction.input.is_some() {
+            Ok(Some(HomeAction::ExtractInput(c).into()))
+        } else if self.filters.input.is_some() {
+            Ok(Some(HomeAction::FilterInput(c).into()))
+        } else if self.search.typing {
+            Ok(Some(HomeAction::SearchInput(c).into()))
+        } else {
+            Ok(None)
         }
-        Ok(None)
+    }
+
+    fn needs_render(&self) -> bool {
+        self.needs_render
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
-        frame.render_widget(Paragraph::new("hello world"), area);
+        self.needs_render = false;
+        let (area, preview_area) = if self.preview.show {
+            let [tree_area, preview_area] = Layout::horizontal([
+                Constraint::Percentage(100 - self.preview.width),
+                Constraint::Percentage(self.preview.width),
+            ])
+            .areas(area);
+            (tree_area, Some(preview_area))
+        } else {
+            (area, None)
+        };
+
+        let title = self
+            .tree
+            .get(self.tree.root())
+            .map(|root| format!(" {} ", root.path.display()))
+            .unwrap_or_default();
+
+        let mut block = Block::bordered().title(title.bold());
+        if self.search.typing || !self.search.query.is_empty() {
+            let position = self
+                .selected()
+                .and_then(|selected| {
+                    self.search
+                        .matches
+                        .iter()
+                        .position(|m| m.handle == selected)
+                })
+                .map_or("-".to_string(), |index| (index + 1).to_string());
+            block = block.title_bottom(format!(
+                " /{}{} [{}/{}] ",
+                self.search.query,
+                if self.search.typing { "▏" } else { "" },
+                position,
+                self.search.matches.len()
+            ));
+        }
+        for status in [
+            self.extract_status(),
+            self.sort_status(),
+            self.filter_status(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            block = block.title_bottom(Line::from(status).right_aligned());
+        }
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        self.update_window(inner.height as usize);
+
+        let [tree_area, columns_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(self.columns_width())])
+                .areas(inner);
+
+        // the list only holds the rows of the window, so the selection is relative to it
+        let mut state = ListState::default().with_selected(
+            self.state
+                .selected()
+                .map(|selected| selected.saturating_sub(self.offset)),
+        );
+        let highlight_style = self.style("selected", SELECTED_STYLE);
+        let list = List::new(self.window.clone())
+            .highlight_style(highlight_style)
+            .highlight_symbol(">  ")
+            .highlight_spacing(HighlightSpacing::Always);
+        frame.render_stateful_widget(list, tree_area, &mut state);
+
+        if self.show_columns {
+            let columns = List::new(self.window_columns.clone()).highlight_style(highlight_style);
+            frame.render_stateful_widget(columns, columns_area, &mut state);
+        }
+        if let Some(preview_area) = preview_area {
+            self.draw_preview(frame, preview_area);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn update(home: &mut Home, action: HomeAction) -> Option<Action> {
+        home.update(action.into()).unwrap()
+    }
+
+    #[test]
+    fn test_pick_marked_or_selected_paths() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("dir"))?;
+        fs::write(dir.path().join("a.txt"), "")?;
+        fs::write(dir.path().join("b.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        // opening a directory does not pick it
+        update(&mut home, HomeAction::SelectNext);
+        assert_eq!(update(&mut home, HomeAction::Open), None);
+
+        update(&mut home, HomeAction::SelectNext);
+        assert_eq!(
+            update(&mut home, HomeAction::Open),
+            Some(AppAction::Pick(vec![dir.path().join("a.txt")]).into())
+        );
+
+        update(&mut home, HomeAction::ToggleMark);
+        update(&mut home, HomeAction::ToggleMark);
+        update(&mut home, HomeAction::SelectFirst);
+        assert_eq!(
+            update(&mut home, HomeAction::Pick),
+            Some(AppAction::Pick(vec![dir.path().join("a.txt"), dir.path().join("b.txt")]).into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_quit_reports_directory_of_selection() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("dir"))?;
+        fs::write(dir.path().join("dir").join("file.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let quit = |home: &mut Home| home.update(AppAction::Quit.into()).unwrap();
+
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        let expected = Some(AppAction::ChangeDir(dir.path().join("dir")).into());
+        assert_eq!(quit(&mut home), expected);
+
+        update(&mut home, HomeAction::SelectNext);
+        assert_eq!(quit(&mut home), expected);
+        Ok(())
+    }
+
+    /// Feed the actions that `home` sent to itself back into it until `done` returns true.
+    async fn settle(
+        home: &mut Home,
+        rx: &mut mpsc::UnboundedReceiver<Action>,
+        done: impl Fn(&Action) -> bool,
+    ) {
+        while let Some(action) = rx.recv().await {
+            let is_done = done(&action);
+            home.update(action).unwrap();
+            if is_done {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expand_loads_in_background() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("dir"))?;
+        fs::write(dir.path().join("dir").join("file.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        let dir_handle = home.selected().unwrap();
+        assert!(matches!(
+            home.tree.get(dir_handle).unwrap().kind,
+            NodeKind::Directory {
+                load: LoadState::Loading,
+                ..
+            }
+        ));
+
+        settle(&mut home, &mut rx, |action| {
+            matches!(action, Action::Home(HomeAction::LoadFinished(..)))
+        })
+        .await;
+        assert_eq!(home.rows.len(), 3);
+        assert!(matches!(
+            home.tree.get(dir_handle).unwrap().kind,
+            NodeKind::Directory {
+                load: LoadState::Loaded,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_measure_all_sorts_by_usage() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("a_small"))?;
+        fs::write(dir.path().join("a_small").join("file.txt"), "x")?;
+        fs::create_dir_all(dir.path().join("b_big").join("nested"))?;
+        fs::write(dir.path().join("b_big").join("one"), vec![1; 64 * 1024])?;
+        fs::write(dir.path().join("b_big").join("nested").join("two"), "x")?;
+        let mut tree = FileTree::new(dir.path())?;
+        tree.set_sort_order(SortOrder {
+            keys: vec!["-size".parse().unwrap()],
+            ..SortOrder::default()
+        });
+        let mut home = Home::new(tree);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+
+        update(&mut home, HomeAction::MeasureAll);
+        assert_eq!(home.measures.len(), 3);
+        let measured = std::cell::Cell::new(0);
+        settle(&mut home, &mut rx, |action| {
+            if matches!(action, Action::Home(HomeAction::Measured(..))) {
+                measured.set(measured.get() + 1);
+            }
+            measured.get() == 3
+        })
+        .await;
+
+        assert!(home.measures.is_empty());
+        assert_eq!(row_names(&home), vec!["b_big", "a_small"]);
+        let usage = home.tree.get(home.rows[1]).unwrap().usage.unwrap();
+        assert_eq!(usage.files, 2);
+        assert!(usage.bytes >= 64 * 1024);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preview_follows_the_selection() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.txt"), "first\nsecond\n")?;
+        fs::write(dir.path().join("b.txt"), "other\n")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+
+        update(&mut home, HomeAction::SelectNext);
+        assert_eq!(home.preview.requested, None);
+        update(&mut home, HomeAction::TogglePreview);
+        assert!(home.preview.is_loading());
+        // the preview of a.txt is outdated by the time it arrives
+        update(&mut home, HomeAction::SelectNext);
+        settle(&mut home, &mut rx, |action| {
+            matches!(
+                action,
+                Action::Home(HomeAction::Previewed(preview)) if preview.path.ends_with("b.txt")
+            )
+        })
+        .await;
+
+        let content = home.preview.content.as_ref().unwrap();
+        assert_eq!(content.path, dir.path().join("b.txt"));
+        assert_eq!(content.lines[0].to_string(), "other");
+        assert!(!home.preview.is_loading());
+
+        update(&mut home, HomeAction::GrowPreview);
+        assert_eq!(home.preview.width, 55);
+        for _ in 0..20 {
+            update(&mut home, HomeAction::ShrinkPreview);
+        }
+        assert_eq!(home.preview.width, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_browse_and_extract_archive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("docs.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&archive)?);
+        for (name, content) in [("docs/guide.md", "# Guide\n"), ("notes.txt", "todo\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes())?;
+        }
+        builder.finish()?;
+        drop(builder);
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        home.preview.show = true;
+
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        assert_eq!(row_names(&home)[1..], ["docs", "notes.txt"]);
+        assert_eq!(
+            home.preview.content.as_ref().unwrap().summary,
+            "tar archive, 2 entries"
+        );
+
+        update(&mut home, HomeAction::SelectLast);
+        let content = home.preview.content.as_ref().unwrap();
+        assert_eq!(content.path, archive.join("notes.txt"));
+        assert_eq!(content.lines[0].to_string(), "todo");
+        // entries of archives cannot be picked
+        assert_eq!(update(&mut home, HomeAction::Open), None);
+
+        let extract_to = |home: &mut Home, dest: &str| {
+            assert_eq!(
+                update(home, HomeAction::StartExtract),
+                Some(AppAction::SetMode(Mode::Extract).into())
+            );
+            let input = home.extraction.input.clone().unwrap();
+            for _ in 0..input.len() {
+                update(home, HomeAction::ExtractBackspace);
+            }
+            for c in dest.chars() {
+                update(home, HomeAction::ExtractInput(c));
+            }
+            update(home, HomeAction::ConfirmExtract);
+        };
+        extract_to(&mut home, "out");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out").join("notes.txt"))?,
+            "todo\n"
+        );
+        assert_eq!(
+            home.extract_status().unwrap(),
+            format!(
+                " extracted 1 entry to {} ",
+                dir.path().join("out").display()
+            )
+        );
+
+        // the file which blocks the extraction is named
+        extract_to(&mut home, "out");
+        assert_eq!(
+            home.extract_status().unwrap(),
+            format!(
+                " failed to extract: {} already exists ",
+                dir.path().join("out").join("notes.txt").display()
+            )
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collapse_cancels_loading() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("dir"))?;
+        fs::write(dir.path().join("dir").join("file.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+
+        update(&mut home, HomeAction::SelectNext);
+        let dir_handle = home.selected().unwrap();
+        update(&mut home, HomeAction::Expand);
+        update(&mut home, HomeAction::Collapse);
+        assert!(home.loads.is_empty());
+
+        // entries which were sent before the load was cancelled are dropped
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        while let Ok(action) = rx.try_recv() {
+            home.update(action)?;
+        }
+        assert_eq!(home.tree.children(dir_handle).count(), 0);
+        assert!(matches!(
+            home.tree.get(dir_handle).unwrap().kind,
+            NodeKind::Directory {
+                load: LoadState::NotLoaded,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filtered_directories_stop_loading() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("dir"))?;
+        fs::write(dir.path().join("dir").join("file.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+
+        update(&mut home, HomeAction::SelectNext);
+        let dir_handle = home.selected().unwrap();
+        update(&mut home, HomeAction::Expand);
+        assert!(home.loads.contains_key(&dir_handle));
+
+        update(&mut home, HomeAction::StartFilter);
+        type_query(&mut home, "!dir");
+        assert!(home.loads.is_empty());
+        assert!(matches!(
+            home.tree.get(dir_handle).unwrap().kind,
+            NodeKind::Directory {
+                load: LoadState::NotLoaded,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_changes_on_disk_are_patched_into_the_tree() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.txt"), "")?;
+        fs::write(dir.path().join("c.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        update(&mut home, HomeAction::SelectLast);
+        let selected = home.selected();
+        fs::write(dir.path().join("b.txt"), "")?;
+        fs::remove_file(dir.path().join("a.txt"))?;
+        // the change is reported like the watcher does, without waiting for the file system
+        update(
+            &mut home,
+            HomeAction::DirsChanged(vec![dir.path().to_path_buf()]),
+        );
+
+        assert_eq!(row_names(&home), vec!["b.txt", "c.txt"]);
+        assert_eq!(home.selected(), selected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_selects_nearest_surviving_ancestor() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("dir/sub"))?;
+        fs::write(dir.path().join("dir/sub/file.txt"), "")?;
+        fs::write(dir.path().join("other.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        for action in [
+            HomeAction::SelectNext,
+            HomeAction::Expand,
+            HomeAction::SelectNext,
+            HomeAction::Expand,
+            HomeAction::SelectNext,
+        ] {
+            update(&mut home, action);
+        }
+        let dir_handle = home.rows[1];
+        assert_eq!(home.rows.len(), 5);
+
+        fs::remove_dir_all(dir.path().join("dir/sub"))?;
+        fs::write(dir.path().join("dir/new.txt"), "")?;
+        update(&mut home, HomeAction::Refresh);
+
+        assert_eq!(home.selected(), Some(dir_handle));
+        assert_eq!(row_names(&home), vec!["dir", "new.txt", "other.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rows_of_the_changed_directory_are_spliced() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("a").join("nested"))?;
+        fs::write(dir.path().join("a").join("nested").join("deep.txt"), "")?;
+        fs::write(dir.path().join("a").join("file.txt"), "")?;
+        fs::write(dir.path().join("b.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        assert_eq!(home.rows, home.tree.flatten());
+        assert_eq!(home.rows.len(), 6);
+        assert_eq!(selected_name(&home), "nested");
+
+        update(&mut home, HomeAction::SelectPrev);
+        update(&mut home, HomeAction::Collapse);
+        assert_eq!(home.rows, home.tree.flatten());
+        assert_eq!(home.rows.len(), 3);
+        assert_eq!(selected_name(&home), "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_only_renders_visible_rows() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for i in 0..100 {
+            fs::write(dir.path().join(format!("{i:03}.txt")), "")?;
+        }
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 10))?;
+
+        update(&mut home, HomeAction::SelectLast);
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert_eq!(home.window.len(), 8);
+        assert_eq!(home.offset, 93);
+        let last_line: String = (0..40)
+            .map(|x| terminal.backend().buffer()[(x, 8)].symbol().to_string())
+            .collect();
+        assert!(last_line.contains("099.txt"));
+
+        assert!(!home.needs_render());
+
+        // moving inside of the window keeps it where it is
+        update(&mut home, HomeAction::SelectPrev);
+        assert!(home.needs_render());
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert_eq!(home.offset, 93);
+
+        update(&mut home, HomeAction::SelectFirst);
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert_eq!(home.offset, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unchanged_view_is_not_rendered_again() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("a"))?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        home.needs_render = false;
+
+        // the selection is already on the first row
+        update(&mut home, HomeAction::SelectPrev);
+        assert!(!home.needs_render());
+
+        // a batch of a load that is not in flight anymore is dropped
+        let a = home.rows[1];
+        let stale = LoadId { dir: a, id: 0 };
+        let batch = NodeBatch(read_entries(
+            &dir.path().join("a"),
+            home.tree.ignore_rules(),
+        )?);
+        update(&mut home, HomeAction::Loaded(stale, batch));
+        update(&mut home, HomeAction::LoadFinished(stale, None));
+        assert!(!home.needs_render());
+
+        update(&mut home, HomeAction::SelectNext);
+        assert!(home.needs_render());
+        Ok(())
+    }
+
+    fn selected_name(home: &Home) -> String {
+        let selected = home.selected().unwrap();
+        home.tree.get(selected).unwrap().name().into_owned()
+    }
+
+    /// The names of the visible rows below the root.
+    fn row_names(home: &Home) -> Vec<String> {
+        home.rows
+            .iter()
+            .skip(1)
+            .map(|row| home.tree.get(*row).unwrap().name().into_owned())
+            .collect()
+    }
+
+    fn type_query(home: &mut Home, query: &str) {
+        for c in query.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty());
+            if let Some(action) = home.handle_key_event(key).unwrap() {
+                home.update(action).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_reveals_matches_in_unloaded_directories() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("dir/sub"))?;
+        fs::write(dir.path().join("dir/sub/needle.txt"), "")?;
+        fs::write(dir.path().join("other.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        assert_eq!(
+            update(&mut home, HomeAction::StartSearch),
+            Some(AppAction::SetMode(Mode::Search).into())
+        );
+        type_query(&mut home, "needle");
+        assert!(home.search.matches.is_empty());
+
+        update(&mut home, HomeAction::SearchAll);
+        assert_eq!(selected_name(&home), "needle.txt");
+        assert_eq!(home.rows.len(), 5);
+
+        assert_eq!(
+            update(&mut home, HomeAction::ConfirmSearch),
+            Some(AppAction::SetMode(Mode::Home).into())
+        );
+        // the query is not changed by keys after it was confirmed
+        type_query(&mut home, "x");
+        assert_eq!(home.search.query, "needle");
+        assert_eq!(home.search.matches.len(), 1);
+
+        assert_eq!(update(&mut home, HomeAction::CancelSearch), None);
+        assert!(home.search.matches.is_empty());
+        assert_eq!(selected_name(&home), "needle.txt");
+        Ok(())
+    }
+
+    #[test]
+    fn test_jump_between_matches_and_cancel() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["a_match.txt", "b.txt", "c_match.txt"] {
+            fs::write(dir.path().join(name), "")?;
+        }
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        update(&mut home, HomeAction::SelectNext);
+
+        update(&mut home, HomeAction::StartSearch);
+        type_query(&mut home, "mat");
+        assert_eq!(home.search.matches.len(), 2);
+
+        update(&mut home, HomeAction::SelectFirst);
+        update(&mut home, HomeAction::NextMatch);
+        assert_eq!(selected_name(&home), "a_match.txt");
+        update(&mut home, HomeAction::NextMatch);
+        assert_eq!(selected_name(&home), "c_match.txt");
+        update(&mut home, HomeAction::NextMatch);
+        assert_eq!(selected_name(&home), "a_match.txt");
+        update(&mut home, HomeAction::PrevMatch);
+        assert_eq!(selected_name(&home), "c_match.txt");
+
+        update(&mut home, HomeAction::SearchBackspace);
+        assert_eq!(home.search.query, "ma");
+
+        // cancelling while typing goes back to where the search started
+        assert_eq!(
+            update(&mut home, HomeAction::CancelSearch),
+            Some(AppAction::SetMode(Mode::Home).into())
+        );
+        assert_eq!(selected_name(&home), "a_match.txt");
+        assert_eq!(home.search.query, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_typed_patterns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("target"))?;
+        fs::write(dir.path().join("target").join("build.rs"), "")?;
+        fs::write(dir.path().join("a.rs"), "")?;
+        fs::write(dir.path().join("b.txt"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        update(&mut home, HomeAction::StartFilter);
+        type_query(&mut home, "*.rs !target");
+        assert_eq!(row_names(&home), vec!["a.rs"]);
+
+        // invalid patterns keep the last valid filter until they are corrected
+        type_query(&mut home, " a[");
+        assert_eq!(row_names(&home), vec!["a.rs"]);
+        assert_eq!(update(&mut home, HomeAction::ConfirmFilter), None);
+        assert!(home.filters.error.is_some());
+        for _ in 0..3 {
+            update(&mut home, HomeAction::FilterBackspace);
+        }
+        assert_eq!(
+            update(&mut home, HomeAction::ConfirmFilter),
+            Some(AppAction::SetMode(Mode::Home).into())
+        );
+        assert_eq!(
+            home.filter_status().as_deref(),
+            Some(" filter: *.rs !target ")
+        );
+
+        update(&mut home, HomeAction::ToggleFilters);
+        assert_eq!(row_names(&home), vec!["target", "a.rs", "b.txt"]);
+        assert_eq!(home.filter_status().as_deref(), Some(" filters off "));
+
+        // the prompt starts with the active patterns and cancelling keeps them
+        update(&mut home, HomeAction::ToggleFilters);
+        update(&mut home, HomeAction::StartFilter);
+        assert_eq!(home.filters.input.as_deref(), Some("*.rs !target"));
+        type_query(&mut home, " b*");
+        assert_eq!(row_names(&home), vec!["a.rs", "b.txt"]);
+        update(&mut home, HomeAction::CancelFilter);
+        assert_eq!(row_names(&home), vec!["a.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_toggle_ignored_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join(".git"))?;
+        fs::create_dir(dir.path().join("target"))?;
+        fs::write(dir.path().join(".gitignore"), "target/\n")?;
+        fs::write(dir.path().join("main.rs"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+
+        let config = Config {
+            ignored: IgnoredEntries::Hide,
+            ..Config::default()
+        };
+        home.register_config_handler(config)?;
+        assert_eq!(row_names(&home), vec![".git", ".gitignore", "main.rs"]);
+
+        update(&mut home, HomeAction::ToggleIgnored);
+        assert_eq!(
+            row_names(&home),
+            vec![".git", "target", ".gitignore", "main.rs"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_sort_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(dir.path().join("sub").join("b"), "")?;
+        fs::write(dir.path().join("sub").join("a"), "1")?;
+        fs::write(dir.path().join("file10"), "1")?;
+        fs::write(dir.path().join("file9"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::Expand);
+        assert_eq!(row_names(&home), vec!["sub", "a", "b", "file9", "file10"]);
+
+        assert_eq!(
+            update(&mut home, HomeAction::StartSort),
+            Some(AppAction::SetMode(Mode::Sort).into())
+        );
+        update(&mut home, HomeAction::SortBySize);
+        update(&mut home, HomeAction::ReverseSort);
+        assert_eq!(row_names(&home), vec!["sub", "a", "b", "file10", "file9"]);
+        assert_eq!(
+            home.sort_status().as_deref(),
+            Some(" sort: -size, name, dirs first ")
+        );
+        update(&mut home, HomeAction::StopSort);
+
+        // only the directory around the selection is sorted differently
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::StartDirSort);
+        update(&mut home, HomeAction::SortByName);
+        update(&mut home, HomeAction::ReverseSort);
+        assert_eq!(row_names(&home), vec!["sub", "b", "a", "file10", "file9"]);
+        assert_eq!(selected_name(&home), "a");
+        update(&mut home, HomeAction::ResetSort);
+        assert_eq!(row_names(&home), vec!["sub", "a", "b", "file10", "file9"]);
+
+        update(&mut home, HomeAction::StopSort);
+        update(&mut home, HomeAction::StartSort);
+        update(&mut home, HomeAction::ResetSort);
+        assert_eq!(row_names(&home), vec!["sub", "a", "b", "file9", "file10"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_sort_order_is_saved() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("file"), "")?;
+        let config_dir = tempfile::tempdir()?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        home.config.config_dir = Some(config_dir.path().to_path_buf());
+
+        update(&mut home, HomeAction::StartSort);
+        update(&mut home, HomeAction::SortBySize);
+        update(&mut home, HomeAction::StopSort);
+        // the directory around the selection
+        update(&mut home, HomeAction::SelectNext);
+        update(&mut home, HomeAction::StartDirSort);
+        update(&mut home, HomeAction::ReverseSort);
+        update(&mut home, HomeAction::StopSort);
+
+        let (config, _) = Config::load(config_dir.path())?;
+        assert_eq!(config.sort.to_string(), "size, name, dirs first");
+        assert_eq!(
+            config.dir_sorts[dir.path()].to_string(),
+            "-size, name, dirs first"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_status_markers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src").join("new.rs"), "")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        home.config
+            .styles
+            .entry(Mode::Home)
+            .or_default()
+            .insert("git_untracked".to_string(), Style::new().fg(Color::Red));
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(30, 5))?;
+
+        // the status is only read once the tree is refreshed or the app starts
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert_eq!(home.git, None);
+        update(&mut home, HomeAction::Refresh);
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+
+        // the repository itself comes first
+        let line: String = (0..30)
+            .map(|x| terminal.backend().buffer()[(x, 3)].symbol().to_string())
+            .collect();
+        assert!(line.contains("src ?"), "{line}");
+        let marker = line.chars().position(|c| c == '?').unwrap();
+        assert_eq!(
+            terminal.backend().buffer()[(marker as u16, 3)].fg,
+            Color::Red
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_git_status_is_read_once_per_burst_of_changes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        fs::create_dir(dir.path().join("target"))?;
+        fs::write(dir.path().join(".gitignore"), "target\n")?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        home.register_action_handler(tx)?;
+        let git_read = |action: &Action| matches!(action, Action::Home(HomeAction::GitRead(..)));
+
+        // changes while the status is read lead to a single read afterwards
+        for _ in 0..3 {
+            update(
+                &mut home,
+                HomeAction::DirsChanged(vec![dir.path().to_path_buf()]),
+            );
+        }
+        settle(&mut home, &mut rx, git_read).await;
+        assert!(home.git.is_some());
+        assert!(home.git_token.is_some());
+        assert!(!home.git_stale);
+        settle(&mut home, &mut rx, git_read).await;
+        assert!(home.git_token.is_none());
+
+        // ignored directories do not change the status
+        update(
+            &mut home,
+            HomeAction::DirsChanged(vec![dir.path().join("target")]),
+        );
+        assert!(home.git_token.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_detail_columns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("file.txt"), "x".repeat(2048))?;
+        let mut home = Home::new(FileTree::new(dir.path())?);
+        let config = Config {
+            columns: Columns {
+                order: vec![Column::Links, Column::Size],
+                widths: HashMap::from([(Column::Size, 6)]),
+                ..Columns::default()
+            },
+            ..Config::default()
+        };
+        home.register_config_handler(config)?;
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 5))?;
+        let line = |terminal: &ratatui::Terminal<ratatui::backend::TestBackend>| -> String {
+            (0..40)
+                .map(|x| terminal.backend().buffer()[(x, 2)].symbol().to_string())
+                .collect()
+        };
+
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert!(!line(&terminal).contains("2.0K"));
+
+        update(&mut home, HomeAction::ToggleColumns);
+        terminal.draw(|frame| home.draw(frame, frame.area()).unwrap())?;
+        assert_eq!(home.columns_width(), 4 + 7);
+        // the columns are right-aligned at the border
+        assert!(line(&terminal).ends_with("   1   2.0K│"));
+        assert!(line(&terminal).contains("file.txt"));
         Ok(())
     }
 }
@@ -106,6 +106,17 @@ pub trait Component {
     fn update(&mut self, _action: Action) -> Result<Option<Action>> {
         Ok(None)
     }
+    /// Check whether the component changed since it was drawn the last time.
+    ///
+    /// On a render tick the screen is only redrawn if any component needs it, so a component
+    /// must return `true` here after every change to what it draws until `draw` is called.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the screen has to be redrawn.
+    fn needs_render(&self) -> bool {
+        false
+    }
     /// Render the component on the screen. (REQUIRED)
     ///
     /// # Arguments
@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+
+use crate::{
+    cli::ConfigFormat,
+    config::{
+        CONFIG_FILES, Config, colors::style_to_string, get_config_dir,
+        keys::key_sequence_to_string, saved::DirSortSettings,
+    },
+};
+
+/// The merged configuration in the notation of the config files.
+#[derive(Debug, Serialize)]
+struct EffectiveConfig {
+    // plain values have to come before the tables for TOML
+    filters: Vec<String>,
+    ignored: String,
+    sort: EffectiveSort,
+    columns: EffectiveColumns,
+    preview: EffectivePreview,
+    keybindings: BTreeMap<String, BTreeMap<String, String>>,
+    styles: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct EffectiveSort {
+    by: Vec<String>,
+    dirs_first: bool,
+    ignore_case: bool,
+    dirs: Vec<DirSortSettings>,
+}
+
+#[derive(Debug, Serialize)]
+struct EffectiveColumns {
+    show: bool,
+    order: Vec<String>,
+    widths: BTreeMap<String, u16>,
+}
+
+#[derive(Debug, Serialize)]
+struct EffectivePreview {
+    show: bool,
+    width: u16,
+    lines: usize,
+    theme: String,
+}
+
+impl From<&Config> for EffectiveConfig {
+    fn from(config: &Config) -> Self {
+        let keybindings = config
+            .keybindings
+            .iter()
+            .map(|(mode, bindings)| {
+                let bindings = bindings
+                    .iter()
+                    .map(|(keys, action)| (key_sequence_to_string(keys), action.to_string()))
+                    .collect();
+                (mode.to_string(), bindings)
+            })
+            .collect();
+        let styles = config
+            .styles
+            .iter()
+            .map(|(mode, styles)| {
+                let styles = styles
+                    .iter()
+                    .map(|(name, style)| (name.clone(), style_to_string(style)))
+                    .collect();
+                (mode.to_string(), styles)
+            })
+            .collect();
+        let mut dir_sorts: Vec<_> = config
+            .dir_sorts
+            .iter()
+            .map(|(path, order)| DirSortSettings::new(path, order))
+            .collect();
+        dir_sorts.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Self {
+            filters: config.filters.iter().map(ToString::to_string).collect(),
+            ignored: config.ignored.to_string(),
+            sort: EffectiveSort {
+                by: config.sort.keys.iter().map(ToString::to_string).collect(),
+                dirs_first: config.sort.dirs_first,
+                ignore_case: config.sort.ignore_case,
+                dirs: dir_sorts,
+            },
+            columns: EffectiveColumns {
+                show: config.columns.show,
+                order: config
+                    .columns
+                    .order
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                widths: config
+                    .columns
+                    .order
+                    .iter()
+                    .map(|column| (column.to_string(), config.columns.width(*column)))
+                    .collect(),
+            },
+            preview: EffectivePreview {
+                show: config.preview.show,
+                width: config.preview.width,
+                lines: config.preview.lines,
+                theme: config.preview.theme.clone(),
+            },
+            keybindings,
+            styles,
+        }
+    }
+}
+
+/// Validate the configuration and print the effective result to stdout.
+///
+/// Without a `file` all config files of the config directory are checked. Every problem is
+/// reported on stderr and makes the check fail.
+pub fn check_config(file: Option<&Path>, format: ConfigFormat) -> Result<ExitCode> {
+    let (config, diagnostics) = match file {
+        Some(file) => Config::from_files(&[(file.to_path_buf(), file_format(file)?)])?,
+        None => Config::load(&get_config_dir())?,
+    };
+
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+
+    let effective = EffectiveConfig::from(&config);
+    let output = match format {
+        ConfigFormat::Json5 => serde_json::to_string_pretty(&effective)?,
+        ConfigFormat::Toml => toml::to_string_pretty(&effective)?,
+    };
+    println!("{output}");
+
+    if diagnostics.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Determine the format of a config file from its name, e.g. `config.toml`.
+fn file_format(file: &Path) -> Result<config::FileFormat> {
+    if !file.exists() {
+        return Err(eyre!("{} does not exist", file.display()));
+    }
+    let extension = file.extension().unwrap_or_default();
+    CONFIG_FILES
+        .iter()
+        .find(|(name, _)| PathBuf::from(name).extension() == Some(extension))
+        .map(|(_, format)| *format)
+        .ok_or_else(|| eyre!("unsupported config format: {}", file.display()))
+}
@@ -1,60 +1,116 @@
-use color_eyre::Result;
 use derive_deref::{Deref, DerefMut};
 use ratatui::style::{Color, Modifier, Style};
-use serde::{Deserialize, de::Deserializer};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
-use crate::app::Mode;
+use crate::{
+    app::Mode,
+    config::{Diagnostic, parse_mode},
+};
 
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
 pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
 
-impl<'de> Deserialize<'de> for Styles {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
-
-        let styles = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(str, style)| (str, parse_style(&style)))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
-
-        Ok(Styles(styles))
+impl Styles {
+    /// Validate the styles of a config file and skip all invalid entries.
+    pub(super) fn from_raw(
+        raw: HashMap<String, HashMap<String, String>>,
+        file: &Path,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let mut styles = HashMap::new();
+
+        for (raw_mode, raw_styles) in raw {
+            let Some(mode) = parse_mode(&raw_mode, "styles", file, diagnostics) else {
+                continue;
+            };
+            let mode_styles: &mut HashMap<_, _> = styles.entry(mode).or_default();
+
+            for (name, line) in raw_styles {
+                match parse_style(&line) {
+                    Ok(style) => {
+                        mode_styles.insert(name, style);
+                    }
+                    Err(err) => diagnostics.push(Diagnostic::new(
+                        file,
+                        format!("styles.{raw_mode}.{name}"),
+                        err,
+                    )),
+                }
+            }
+        }
+
+        Styles(styles)
     }
 }
 
-pub fn parse_style(line: &str) -> Style {
+pub fn parse_style(line: &str) -> Result<Style, String> {
     let (foreground, background) =
         line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
     let foreground = process_color_string(foreground);
     let background = process_color_string(&background.replace("on ", ""));
 
     let mut style = Style::default();
-    if let Some(fg) = parse_color(&foreground.0) {
+    if let Some(fg) = parse_color_or_none(&foreground.0)? {
         style = style.fg(fg);
     }
-    if let Some(bg) = parse_color(&background.0) {
+    if let Some(bg) = parse_color_or_none(&background.0)? {
         style = style.bg(bg);
     }
     style = style.add_modifier(foreground.1 | background.1);
-    style
+    Ok(style)
+}
+
+/// Parse a color where an empty string means that no color is set.
+fn parse_color_or_none(s: &str) -> Result<Option<Color>, String> {
+    if s.trim().is_empty() {
+        return Ok(None);
+    }
+    match parse_color(s) {
+        Some(color) => Ok(Some(color)),
+        None => Err(format!("unknown color `{}`", s.trim())),
+    }
+}
+
+/// Format a style in the same notation that is accepted by [`parse_style`].
+pub fn style_to_string(style: &Style) -> String {
+    let mut parts = Vec::new();
+    if style.add_modifier.contains(Modifier::BOLD) {
+        parts.push("bold".to_string());
+    }
+    if style.add_modifier.contains(Modifier::UNDERLINED) {
+        parts.push("underline".to_string());
+    }
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        parts.push("inverse".to_string());
+    }
+    if let Some(fg) = style.fg {
+        parts.push(color_to_string(fg));
+    }
+    if let Some(bg) = style.bg {
+        parts.push(format!("on {}", color_to_string(bg)));
+    }
+    parts.join(" ")
+}
+
+fn color_to_string(color: Color) -> String {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    match color {
+        Color::Indexed(c) if (c as usize) < NAMES.len() => NAMES[c as usize].to_string(),
+        Color::Indexed(c) if c >= 232 => format!("gray{}", c - 232),
+        Color::Indexed(c) => format!("color{c}"),
+        color => color.to_string().to_lowercase(),
+    }
 }
 
 fn process_color_string(color_str: &str) -> (String, Modifier) {
     let color = color_str
         .replace("grey", "gray")
-        .replace("bright ", "")
-        .replace("bold ", "")
-        .replace("underline ", "")
-        .replace("inverse ", "");
+        .split_whitespace()
+        .filter(|word| !matches!(*word, "bright" | "bold" | "underline" | "inverse"))
+        .collect::<Vec<_>>()
+        .join(" ");
 
     let mut modifiers = Modifier::empty();
     if color_str.contains("underline") {
@@ -143,29 +199,55 @@ mod tests {
 
     #[test]
     fn test_parse_style_default() {
-        let style = parse_style("");
+        let style = parse_style("").unwrap();
         assert_eq!(style, Style::default());
     }
 
     #[test]
     fn test_parse_style_foreground() {
-        let style = parse_style("red");
+        let style = parse_style("red").unwrap();
         assert_eq!(style.fg, Some(Color::Indexed(1)));
     }
 
     #[test]
     fn test_parse_style_background() {
-        let style = parse_style("on blue");
+        let style = parse_style("on blue").unwrap();
         assert_eq!(style.bg, Some(Color::Indexed(4)));
     }
 
     #[test]
     fn test_parse_style_modifiers() {
-        let style = parse_style("underline red on blue");
+        let style = parse_style("underline red on blue").unwrap();
         assert_eq!(style.fg, Some(Color::Indexed(1)));
         assert_eq!(style.bg, Some(Color::Indexed(4)));
     }
 
+    #[test]
+    fn test_parse_style_only_modifiers() {
+        let style = parse_style("bold").unwrap();
+        assert_eq!(style, Style::default().add_modifier(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_parse_style_unknown_color() {
+        assert!(parse_style("redd").is_err());
+        assert!(parse_style("red on bleu").is_err());
+    }
+
+    #[test]
+    fn test_style_to_string_roundtrip() {
+        for raw in [
+            "",
+            "red",
+            "on blue",
+            "inverse",
+            "bold underline gray3 on color42",
+        ] {
+            let style = parse_style(raw).unwrap();
+            assert_eq!(parse_style(&style_to_string(&style)).unwrap(), style);
+        }
+    }
+
     #[test]
     fn test_process_color_string() {
         let (color, modifiers) = process_color_string("underline bold inverse gray");
@@ -1,35 +1,56 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use color_eyre::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use derive_deref::{Deref, DerefMut};
 
-use serde::{Deserialize, de::Deserializer};
-
-use crate::{action::AppAction, app::Mode};
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{Diagnostic, parse_mode},
+};
 
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
-pub struct KeyBindings(pub HashMap<Mode, HashMap<Vec<KeyEvent>, AppAction>>);
-
-impl<'de> Deserialize<'de> for KeyBindings {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let parsed_map = HashMap::<Mode, HashMap<String, AppAction>>::deserialize(deserializer)?;
-
-        let keybindings = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
-
-        Ok(KeyBindings(keybindings))
+pub struct KeyBindings(pub HashMap<Mode, HashMap<Vec<KeyEvent>, Action>>);
+
+impl KeyBindings {
+    /// Validate the keybindings of a config file and skip all invalid entries.
+    pub(super) fn from_raw(
+        raw: HashMap<String, HashMap<String, String>>,
+        file: &Path,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Self {
+        let mut keybindings = HashMap::new();
+
+        for (raw_mode, raw_bindings) in raw {
+            let Some(mode) = parse_mode(&raw_mode, "keybindings", file, diagnostics) else {
+                continue;
+            };
+            let bindings: &mut HashMap<_, _> = keybindings.entry(mode).or_default();
+
+            for (key_str, action_str) in raw_bindings {
+                let key = format!("keybindings.{raw_mode}.{key_str}");
+                let sequence = match parse_key_sequence(&key_str) {
+                    Ok(sequence) => sequence,
+                    Err(err) => {
+                        diagnostics.push(Diagnostic::new(file, key, err));
+                        continue;
+                    }
+                };
+                match action_str.parse::<Action>() {
+                    Ok(action) => {
+                        bindings.insert(sequence, action);
+                    }
+                    Err(err) => diagnostics.push(Diagnostic::new(
+                        file,
+                        key,
+                        format!("invalid action `{action_str}`: {err}"),
+                    )),
+                }
+            }
+        }
+
+        KeyBindings(keybindings)
     }
 }
 
@@ -132,7 +153,7 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
         KeyCode::Delete => "delete",
         KeyCode::Insert => "insert",
         KeyCode::F(c) => {
-            char = format!("f({c})");
+            char = format!("f{c}");
             &char
         }
         KeyCode::Char(' ') => "space",
@@ -177,14 +198,21 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     key
 }
 
+/// Format a key sequence in the same notation that is used in the config, e.g. `<ctrl-a><b>`.
+pub fn key_sequence_to_string(sequence: &[KeyEvent]) -> String {
+    sequence
+        .iter()
+        .map(|key_event| format!("<{}>", key_event_to_string(key_event)))
+        .collect()
+}
+
 pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
         return Err(format!("Unable to parse `{}`", raw));
     }
     let raw = if !raw.contains("><") {
         let raw = raw.strip_prefix('<').unwrap_or(raw);
-        let raw = raw.strip_prefix('>').unwrap_or(raw);
-        raw
+        raw.strip_prefix('>').unwrap_or(raw)
     } else {
         raw
     };
@@ -210,10 +238,10 @@ mod tests {
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use pretty_assertions::assert_eq;
 
-    use crate::config::Config;
+    use crate::{action::AppAction, components::home::HomeAction, config::Config};
 
     use super::*;
-    
+
     #[test]
     fn test_config() -> Result<()> {
         let c = Config::new()?;
@@ -223,11 +251,19 @@ mod tests {
                 .unwrap()
                 .get(&parse_key_sequence("<q>").unwrap_or_default())
                 .unwrap(),
-            &AppAction::Quit
+            &Action::App(AppAction::Quit)
+        );
+        assert_eq!(
+            c.keybindings
+                .get(&Mode::Home)
+                .unwrap()
+                .get(&parse_key_sequence("<l>").unwrap_or_default())
+                .unwrap(),
+            &Action::Home(HomeAction::Expand)
         );
         Ok(())
     }
-    
+
     #[test]
     fn test_simple_keys() {
         assert_eq!(
@@ -291,6 +327,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_sequence_roundtrip() {
+        for raw in ["<ctrl-a><b>", "<f5>", "<shift-G>", "<space><enter>"] {
+            let sequence = parse_key_sequence(raw).unwrap();
+            let formatted = key_sequence_to_string(&sequence);
+            assert_eq!(parse_key_sequence(&formatted).unwrap(), sequence);
+        }
+    }
+
     #[test]
     fn test_invalid_keys() {
         assert!(parse_key_event("invalid-key").is_err());
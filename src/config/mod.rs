@@ -1,16 +1,50 @@
+mod check;
 mod colors;
-mod keys;
+pub mod keys;
+mod saved;
+mod watch;
 
-use std::{env, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env, fmt,
+    path::{Path, PathBuf},
+};
 
 use color_eyre::Result;
 use directories::ProjectDirs;
 use serde::Deserialize;
 use tracing::error;
 
-use crate::config::{colors::Styles, keys::KeyBindings};
+pub use crate::config::{
+    check::check_config,
+    saved::{save_dir_sort, save_global_sort},
+    watch::ConfigWatcher,
+};
+use crate::{
+    app::Mode,
+    config::{colors::Styles, keys::KeyBindings, saved::SAVED_SORT_FILE},
+    preview::{PreviewSettings, theme_names},
+    tree::{
+        details::{Column, Columns},
+        filter::Pattern,
+        ignore::IgnoredEntries,
+        sort::SortOrder,
+    },
+};
 
 const DEFAULT_CONFIG: &str = include_str!("../../.config/config.json5");
+const DEFAULT_CONFIG_NAME: &str = "<default config>";
+
+/// The share of the view which the preview can take, in percent.
+pub const PREVIEW_WIDTHS: std::ops::RangeInclusive<u16> = 10..=90;
+
+const CONFIG_FILES: [(&str, config::FileFormat); 5] = [
+    ("config.json5", config::FileFormat::Json5),
+    ("config.json", config::FileFormat::Json),
+    ("config.yaml", config::FileFormat::Yaml),
+    ("config.toml", config::FileFormat::Toml),
+    ("config.ini", config::FileFormat::Ini),
+];
 
 /* #[derive(Clone, Debug, Deserialize, Default)]
 pub struct AppConfig {
@@ -20,47 +54,192 @@ pub struct AppConfig {
     config_dir: PathBuf,
 } */
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default)]
 pub struct Config {
     // #[serde(default, flatten)]
     // pub config: AppConfig,
-    #[serde(default)]
     pub keybindings: KeyBindings,
-    #[serde(default)]
     pub styles: Styles,
+    /// the filter patterns of the tree, see [`Pattern`] for their syntax
+    pub filters: Vec<Pattern>,
+    /// how entries are shown which are ignored by git or an `.ignore` file
+    pub ignored: IgnoredEntries,
+    /// the order of the entries of every directory
+    pub sort: SortOrder,
+    /// the orders of single directories, by their absolute path
+    pub dir_sorts: HashMap<PathBuf, SortOrder>,
+    /// the detail columns beside the tree
+    pub columns: Columns,
+    /// the preview of the selected entry
+    pub preview: PreviewSettings,
+    /// the directory the config was loaded from, where the orders changed in the view are
+    /// saved
+    pub config_dir: Option<PathBuf>,
+}
+
+/// The configuration as it is written in a file, before any of its values are validated.
+#[derive(Debug, Default, Deserialize)]
+struct RawConfig {
+    #[serde(default)]
+    keybindings: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    styles: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    filters: Vec<String>,
+    ignored: Option<String>,
+    sort: Option<RawSort>,
+    columns: Option<RawColumns>,
+    preview: Option<RawPreview>,
+}
+
+/// The detail columns beside the tree, see [`Columns`].
+#[derive(Debug, Default, Deserialize)]
+struct RawColumns {
+    show: Option<bool>,
+    order: Option<Vec<String>>,
+    widths: Option<HashMap<String, u16>>,
+}
+
+/// The preview of the selected entry, see [`PreviewSettings`].
+#[derive(Debug, Default, Deserialize)]
+struct RawPreview {
+    show: Option<bool>,
+    width: Option<u16>,
+    lines: Option<usize>,
+    theme: Option<String>,
+}
+
+/// The default order of the entries of every directory, see [`SortOrder`].
+#[derive(Debug, Default, Deserialize)]
+struct RawSort {
+    /// the keys to sort by, e.g. `["-mtime", "name"]`
+    by: Option<Vec<String>>,
+    dirs_first: Option<bool>,
+    ignore_case: Option<bool>,
+    /// the orders of single directories, which is a list because the config crate lowercases
+    /// the keys of tables
+    dirs: Option<Vec<RawDirSort>>,
+}
+
+/// The order of a single directory, which starts from the default order.
+#[derive(Debug, Deserialize)]
+struct RawDirSort {
+    path: PathBuf,
+    by: Option<Vec<String>>,
+    dirs_first: Option<bool>,
+    ignore_case: Option<bool>,
+}
+
+/// A problem with a single entry of a configuration file.
+///
+/// Invalid entries are skipped while loading, so lsn falls back to the defaults for them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// the path to the invalid entry, e.g. `keybindings.Home.<ctrl-x>`
+    pub key: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(file: &Path, key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}: {}", self.file.display(), self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.file.display(), self.key, self.message)
+        }
+    }
 }
 
 impl Config {
-    pub fn new() -> Result<Self, config::ConfigError> {
-        let default_config: Config = json5::from_str(DEFAULT_CONFIG).unwrap();
-        let data_dir = get_data_dir();
-        let config_dir = get_config_dir();
-        let mut builder = config::Config::builder()
-            .set_default("data_dir", data_dir.to_str().unwrap())?
-            .set_default("config_dir", config_dir.to_str().unwrap())?;
-
-        let config_files = [
-            ("config.json5", config::FileFormat::Json5),
-            ("config.json", config::FileFormat::Json),
-            ("config.yaml", config::FileFormat::Yaml),
-            ("config.toml", config::FileFormat::Toml),
-            ("config.ini", config::FileFormat::Ini),
-        ];
-        let mut found_config = false;
-        for (file, format) in &config_files {
-            let source = config::File::from(config_dir.join(file))
-                .format(*format)
-                .required(false);
-            builder = builder.add_source(source);
-            if config_dir.join(file).exists() {
-                found_config = true
-            }
+    /// Load the configuration from the config directory.
+    ///
+    /// Problems with the configuration are reported on stderr, so this must be called before
+    /// the terminal is taken over. Use [`Config::load`] to get hold of them instead.
+    pub fn new() -> Result<Self> {
+        let (config, diagnostics) = Self::load(&get_config_dir())?;
+        for diagnostic in diagnostics {
+            error!("Invalid configuration: {}", diagnostic);
+            eprintln!("lsn: invalid configuration: {diagnostic}");
         }
-        if !found_config {
+        Ok(config)
+    }
+
+    /// Load and validate all config files in `config_dir` and merge them with the defaults.
+    ///
+    /// The orders which were changed in the view are read last, see [`save_global_sort`].
+    pub fn load(config_dir: &Path) -> Result<(Self, Vec<Diagnostic>)> {
+        let mut config_files: Vec<(PathBuf, config::FileFormat)> = CONFIG_FILES
+            .iter()
+            .map(|(file, format)| (config_dir.join(file), *format))
+            .filter(|(path, _)| path.exists())
+            .collect();
+        if config_files.is_empty() {
             error!("No configuration file found. Application may not behave as expected");
         }
+        let saved_sort = config_dir.join(SAVED_SORT_FILE);
+        if saved_sort.exists() {
+            config_files.push((saved_sort, config::FileFormat::Json));
+        }
+        let (mut config, diagnostics) = Self::from_files(&config_files)?;
+        config.config_dir = Some(config_dir.to_path_buf());
+        Ok((config, diagnostics))
+    }
+
+    /// Load and validate the given config files and merge them with the defaults.
+    ///
+    /// Later files take precedence over earlier ones.
+    pub fn from_files(files: &[(PathBuf, config::FileFormat)]) -> Result<(Self, Vec<Diagnostic>)> {
+        let mut diagnostics = Vec::new();
+
+        let mut cfg = Self::default();
+
+        let mut raw_default: RawConfig = json5::from_str(DEFAULT_CONFIG)?;
+        cfg.apply_settings(
+            &mut raw_default,
+            Path::new(DEFAULT_CONFIG_NAME),
+            &mut diagnostics,
+        );
+        let default_config = Self::from_raw(
+            raw_default,
+            Path::new(DEFAULT_CONFIG_NAME),
+            &mut diagnostics,
+        );
 
-        let mut cfg: Self = builder.build()?.try_deserialize()?;
+        for (path, format) in files {
+            let raw = config::Config::builder()
+                .add_source(config::File::from(path.as_path()).format(*format))
+                .build()
+                .and_then(|source| source.try_deserialize::<RawConfig>());
+            let mut raw = match raw {
+                Ok(raw) => raw,
+                Err(err) => {
+                    diagnostics.push(Diagnostic::new(path, "", err.to_string()));
+                    continue;
+                }
+            };
+
+            cfg.apply_settings(&mut raw, path, &mut diagnostics);
+            let file_config = Self::from_raw(raw, path, &mut diagnostics);
+            for (mode, bindings) in file_config.keybindings.0 {
+                cfg.keybindings.entry(mode).or_default().extend(bindings);
+            }
+            for (mode, styles) in file_config.styles.0 {
+                cfg.styles.entry(mode).or_default().extend(styles);
+            }
+            // the filters of all files apply together
+            cfg.filters.extend(file_config.filters);
+        }
 
         for (mode, default_bindings) in default_config.keybindings.iter() {
             let user_bindings = cfg.keybindings.entry(*mode).or_default();
@@ -77,36 +256,425 @@ impl Config {
             }
         }
 
-        Ok(cfg)
+        if cfg.filters.is_empty() {
+            cfg.filters = default_config.filters;
+        }
+
+        diagnostics.sort_by(|a, b| (&a.file, &a.key).cmp(&(&b.file, &b.key)));
+        Ok((cfg, diagnostics))
+    }
+
+    fn from_raw(raw: RawConfig, file: &Path, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        Self {
+            keybindings: KeyBindings::from_raw(raw.keybindings, file, diagnostics),
+            styles: Styles::from_raw(raw.styles, file, diagnostics),
+            filters: parse_filters(raw.filters, file, diagnostics),
+            ..Self::default()
+        }
+    }
+
+    /// Take the plain settings out of `raw` and apply the ones which are set.
+    ///
+    /// Unlike keybindings and styles, they do not fall back to the defaults one by one, so
+    /// they are applied in the order of the files.
+    fn apply_settings(
+        &mut self,
+        raw: &mut RawConfig,
+        file: &Path,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if let Some(ignored) = parse_ignored(raw.ignored.take(), file, diagnostics) {
+            self.ignored = ignored;
+        }
+        if let Some(sort) = raw.sort.take() {
+            apply_sort(&mut self.sort, &mut self.dir_sorts, sort, file, diagnostics);
+        }
+        if let Some(columns) = raw.columns.take() {
+            apply_columns(&mut self.columns, columns, file, diagnostics);
+        }
+        if let Some(preview) = raw.preview.take() {
+            apply_preview(&mut self.preview, preview, file, diagnostics);
+        }
+    }
+}
+
+/// Parse the name of a mode as it is used as a key in the config.
+fn parse_mode(
+    raw: &str,
+    section: &str,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Mode> {
+    match raw.parse() {
+        Ok(mode) => Some(mode),
+        Err(_) => {
+            diagnostics.push(Diagnostic::new(
+                file,
+                format!("{section}.{raw}"),
+                format!("unknown mode `{raw}`"),
+            ));
+            None
+        }
+    }
+}
+
+/// Parse the filter patterns of a config file and skip the invalid ones.
+fn parse_filters(raw: Vec<String>, file: &Path, diagnostics: &mut Vec<Diagnostic>) -> Vec<Pattern> {
+    raw.into_iter()
+        .filter_map(|raw| match Pattern::parse(&raw) {
+            Ok(pattern) => Some(pattern),
+            Err(err) => {
+                diagnostics.push(Diagnostic::new(
+                    file,
+                    "filters",
+                    format!("invalid pattern `{raw}`: {err}"),
+                ));
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_ignored(
+    raw: Option<String>,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<IgnoredEntries> {
+    let raw = raw?;
+    match raw.parse() {
+        Ok(ignored) => Some(ignored),
+        Err(_) => {
+            diagnostics.push(Diagnostic::new(
+                file,
+                "ignored",
+                format!("expected `hide`, `dim` or `show` instead of `{raw}`"),
+            ));
+            None
+        }
+    }
+}
+
+fn apply_sort(
+    order: &mut SortOrder,
+    dir_sorts: &mut HashMap<PathBuf, SortOrder>,
+    mut raw: RawSort,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let dirs = raw.dirs.take().unwrap_or_default();
+    apply_order(order, raw, "sort", file, diagnostics);
+    for raw in dirs {
+        if !raw.path.is_absolute() {
+            diagnostics.push(Diagnostic::new(
+                file,
+                "sort.dirs",
+                format!(
+                    "expected an absolute path instead of `{}`",
+                    raw.path.display()
+                ),
+            ));
+            continue;
+        }
+        let dir_order = dir_sorts.entry(raw.path).or_insert_with(|| order.clone());
+        let raw = RawSort {
+            by: raw.by,
+            dirs_first: raw.dirs_first,
+            ignore_case: raw.ignore_case,
+            dirs: None,
+        };
+        apply_order(dir_order, raw, "sort.dirs", file, diagnostics);
+    }
+}
+
+/// Apply the settings of `raw` which are set to `order`, except for the orders of single
+/// directories. Problems are reported below `section`.
+fn apply_order(
+    order: &mut SortOrder,
+    raw: RawSort,
+    section: &str,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(by) = raw.by {
+        let keys: Vec<_> = by
+            .iter()
+            .filter_map(|key| match key.parse() {
+                Ok(key) => Some(key),
+                Err(err) => {
+                    diagnostics.push(Diagnostic::new(file, format!("{section}.by"), err));
+                    None
+                }
+            })
+            .collect();
+        if !keys.is_empty() {
+            order.keys = keys;
+        }
+    }
+    if let Some(dirs_first) = raw.dirs_first {
+        order.dirs_first = dirs_first;
+    }
+    if let Some(ignore_case) = raw.ignore_case {
+        order.ignore_case = ignore_case;
+    }
+}
+
+fn apply_columns(
+    columns: &mut Columns,
+    raw: RawColumns,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut parse_column = |raw: &str, key: &str| match raw.parse::<Column>() {
+        Ok(column) => Some(column),
+        Err(_) => {
+            diagnostics.push(Diagnostic::new(
+                file,
+                key,
+                format!(
+                    "unknown column `{raw}`, expected size, mtime, permissions, owner, group or links"
+                ),
+            ));
+            None
+        }
+    };
+
+    if let Some(order) = raw.order {
+        columns.order = order
+            .iter()
+            .filter_map(|raw| parse_column(raw, "columns.order"))
+            .collect();
+    }
+    for (raw, width) in raw.widths.unwrap_or_default() {
+        if let Some(column) = parse_column(&raw, &format!("columns.widths.{raw}")) {
+            columns.widths.insert(column, width);
+        }
+    }
+    if let Some(show) = raw.show {
+        columns.show = show;
+    }
+}
+
+fn apply_preview(
+    settings: &mut PreviewSettings,
+    raw: RawPreview,
+    file: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(show) = raw.show {
+        settings.show = show;
+    }
+    match raw.width {
+        Some(width) if !PREVIEW_WIDTHS.contains(&width) => diagnostics.push(Diagnostic::new(
+            file,
+            "preview.width",
+            format!("expected a percentage between 10 and 90 instead of {width}"),
+        )),
+        Some(width) => settings.width = width,
+        None => {}
+    }
+    match raw.lines {
+        Some(0) => diagnostics.push(Diagnostic::new(
+            file,
+            "preview.lines",
+            "expected at least one line",
+        )),
+        Some(lines) => settings.lines = lines,
+        None => {}
+    }
+    if let Some(theme) = raw.theme {
+        if theme_names().any(|name| name == theme) {
+            settings.theme = theme;
+        } else {
+            let names: Vec<_> = theme_names().collect();
+            diagnostics.push(Diagnostic::new(
+                file,
+                "preview.theme",
+                format!(
+                    "unknown theme `{theme}`, expected one of {}",
+                    names.join(", ")
+                ),
+            ));
+        }
     }
 }
 
 pub fn get_data_dir() -> PathBuf {
     let data_folder = env::var("LSN_DATA").ok().map(PathBuf::from);
 
-    let directory = if let Some(data_folder) = data_folder {
+    if let Some(data_folder) = data_folder {
         data_folder
     } else if let Some(proj_dirs) = project_directory() {
         proj_dirs.data_local_dir().to_path_buf()
     } else {
         PathBuf::from(".").join(".data")
-    };
-    directory
+    }
 }
 
 pub fn get_config_dir() -> PathBuf {
-    let config_folder = env::var(format!("LSN_CONFIG")).ok().map(PathBuf::from);
+    let config_folder = env::var("LSN_CONFIG").ok().map(PathBuf::from);
 
-    let directory = if let Some(s) = config_folder {
+    if let Some(s) = config_folder {
         s
     } else if let Some(proj_dirs) = project_directory() {
         proj_dirs.config_local_dir().to_path_buf()
     } else {
         PathBuf::from(".").join(".config")
-    };
-    directory
+    }
 }
 
 fn project_directory() -> Option<ProjectDirs> {
     ProjectDirs::from("de.marma", "", env!("CARGO_PKG_NAME"))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use pretty_assertions::assert_eq;
+    use ratatui::style::{Color, Style};
+
+    use super::*;
+    use crate::action::{Action, AppAction};
+
+    #[test]
+    fn test_default_config_is_valid() -> Result<()> {
+        let (_, diagnostics) = Config::from_files(&[])?;
+        assert_eq!(diagnostics, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_entries_fall_back_to_defaults() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.json5");
+        fs::write(
+            &path,
+            r#"{
+                "keybindings": {
+                    "Home": {
+                        "<x>": "Quit",
+                        "<q>": "Quitt",
+                        "<ctrl-nope>": "Quit",
+                    },
+                    "Nowhere": { "<y>": "Quit" },
+                },
+                "styles": {
+                    "Home": { "selected": "bold redd", "directory": "green" },
+                },
+                "filters": ["!target", "/(/"],
+                "ignored": "fade",
+                "sort": { "by": ["-size", "colour"], "ignore_case": true },
+                "columns": { "order": ["size", "inode"], "widths": { "size": 9 } },
+                "preview": { "width": 95, "lines": 20, "theme": "dracula" },
+            }"#,
+        )?;
+
+        let (config, diagnostics) = Config::load(dir.path())?;
+
+        let keys: Vec<&str> = diagnostics.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "columns.order",
+                "filters",
+                "ignored",
+                "keybindings.home.<ctrl-nope>",
+                "keybindings.home.<q>",
+                "keybindings.nowhere",
+                "preview.theme",
+                "preview.width",
+                "sort.by",
+                "styles.home.selected",
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.file == path));
+
+        let bindings = config.keybindings.get(&Mode::Home).unwrap();
+        let key = |code| vec![KeyEvent::new(code, KeyModifiers::empty())];
+        assert_eq!(
+            bindings.get(&key(KeyCode::Char('x'))),
+            Some(&Action::App(AppAction::Quit))
+        );
+        assert_eq!(
+            bindings.get(&key(KeyCode::Char('q'))),
+            Some(&Action::App(AppAction::Quit))
+        );
+
+        let styles = config.styles.get(&Mode::Home).unwrap();
+        assert_eq!(
+            styles.get("directory"),
+            Some(&Style::default().fg(Color::Indexed(2)))
+        );
+        assert_eq!(styles.get("selected"), None);
+
+        let filters: Vec<String> = config.filters.iter().map(ToString::to_string).collect();
+        assert_eq!(filters, vec!["!target"]);
+        assert_eq!(config.ignored, IgnoredEntries::Dim);
+        assert_eq!(config.sort.to_string(), "-size, dirs first, ignore case");
+        assert_eq!(config.columns.order, vec![Column::Size]);
+        assert_eq!(config.columns.width(Column::Size), 9);
+        assert_eq!(config.preview.width, 50);
+        assert_eq!(config.preview.lines, 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unparsable_file_is_reported() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("config.toml"), "keybindings = [")?;
+
+        let (config, diagnostics) = Config::load(dir.path())?;
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, dir.path().join("config.toml"));
+        assert!(!config.keybindings.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_saved_sort_orders_take_precedence() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("config.json5"),
+            r#"{
+                "sort": {
+                    "by": ["-size"],
+                    "dirs": [
+                        { "path": "relative", "by": ["name"] },
+                        { "path": "/data", "by": ["mtime"] },
+                        { "path": "/Photos", "by": ["name"] },
+                    ],
+                },
+            }"#,
+        )?;
+        let order = |raw: &str, dirs_first| SortOrder {
+            keys: raw.split(' ').map(|key| key.parse().unwrap()).collect(),
+            dirs_first,
+            ignore_case: false,
+        };
+
+        save_global_sort(dir.path(), &order("-name", false))?;
+        save_dir_sort(
+            dir.path(),
+            Path::new("/Photos"),
+            Some(&order("-mtime name", true)),
+        )?;
+        save_dir_sort(dir.path(), Path::new("/gone"), Some(&order("kind", true)))?;
+        save_dir_sort(dir.path(), Path::new("/gone"), None)?;
+        let (config, diagnostics) = Config::load(dir.path())?;
+
+        let keys: Vec<&str> = diagnostics.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, vec!["sort.dirs"]);
+        assert_eq!(config.sort, order("-name", false));
+        // the directories of the config file start from its order
+        let dir_sorts: HashMap<PathBuf, SortOrder> = [
+            (PathBuf::from("/data"), order("mtime", true)),
+            (PathBuf::from("/Photos"), order("-mtime name", true)),
+        ]
+        .into();
+        assert_eq!(config.dir_sorts, dir_sorts);
+        assert_eq!(config.config_dir.as_deref(), Some(dir.path()));
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::tree::sort::SortOrder;
+
+/// The file in the config directory which the orders changed in the view are saved to.
+///
+/// It is read after all other config files, so the saved orders take precedence.
+pub const SAVED_SORT_FILE: &str = "sort.json";
+
+/// The order of a single directory in the notation of the config files, see `sort.dirs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirSortSettings {
+    pub path: PathBuf,
+    pub by: Vec<String>,
+    pub dirs_first: bool,
+    pub ignore_case: bool,
+}
+
+impl DirSortSettings {
+    pub fn new(path: &Path, order: &SortOrder) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            by: order.keys.iter().map(ToString::to_string).collect(),
+            dirs_first: order.dirs_first,
+            ignore_case: order.ignore_case,
+        }
+    }
+}
+
+/// The content of [`SAVED_SORT_FILE`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedConfig {
+    #[serde(default)]
+    sort: SavedSort,
+}
+
+/// The orders which were changed in the view. The global order is only saved once it was
+/// changed, so the config files still decide it until then.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedSort {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    by: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dirs_first: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ignore_case: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dirs: Vec<DirSortSettings>,
+}
+
+/// Save `order` as the order of all directories without an order of their own.
+pub fn save_global_sort(config_dir: &Path, order: &SortOrder) -> Result<()> {
+    update_saved(config_dir, |sort| {
+        sort.by = Some(order.keys.iter().map(ToString::to_string).collect());
+        sort.dirs_first = Some(order.dirs_first);
+        sort.ignore_case = Some(order.ignore_case);
+    })
+}
+
+/// Save `order` as the order of the directory at `dir`, or forget its order if there is none.
+pub fn save_dir_sort(config_dir: &Path, dir: &Path, order: Option<&SortOrder>) -> Result<()> {
+    update_saved(config_dir, |sort| {
+        sort.dirs.retain(|saved| saved.path != dir);
+        if let Some(order) = order {
+            sort.dirs.push(DirSortSettings::new(dir, order));
+            sort.dirs.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+    })
+}
+
+/// Read the saved orders, change them with `update` and write them back.
+fn update_saved(config_dir: &Path, update: impl FnOnce(&mut SavedSort)) -> Result<()> {
+    let path = config_dir.join(SAVED_SORT_FILE);
+    let mut saved = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!("Replacing the unreadable {:?}: {}", path, err);
+            SavedConfig::default()
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => SavedConfig::default(),
+        Err(err) => return Err(err.into()),
+    };
+    update(&mut saved.sort);
+
+    fs::create_dir_all(config_dir)?;
+    fs::write(&path, serde_json::to_string_pretty(&saved)? + "\n")?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::{path::Path, time::Duration};
+
+use color_eyre::Result;
+use notify_debouncer_mini::{
+    DebounceEventResult, Debouncer, new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error};
+
+use crate::{
+    action::{Action, AppAction},
+    config::CONFIG_FILES,
+};
+
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Watches the config directory and sends [`AppAction::ReloadConfig`] when a config file changes.
+///
+/// The directory is watched as long as this value is alive.
+pub struct ConfigWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config_dir: &Path, action_tx: UnboundedSender<Action>) -> Result<Self> {
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |result: DebounceEventResult| {
+            let events = match result {
+                Ok(events) => events,
+                Err(err) => {
+                    error!("Failed to watch the config directory: {:?}", err);
+                    return;
+                }
+            };
+
+            let config_changed = events.iter().any(|event| {
+                event
+                    .path
+                    .file_name()
+                    .is_some_and(|name| CONFIG_FILES.iter().any(|(file, _)| name == *file))
+            });
+            if config_changed {
+                // the receiver is only gone when the app is shutting down
+                let _ = action_tx.send(AppAction::ReloadConfig.into());
+            }
+        })?;
+
+        debouncer
+            .watcher()
+            .watch(config_dir, RecursiveMode::NonRecursive)?;
+        debug!("Watching {:?} for config changes", config_dir);
+
+        Ok(Self {
+            _debouncer: debouncer,
+        })
+    }
+}
@@ -17,10 +17,15 @@ pub fn init() -> Result<()> {
     eyre_hook.install()?;
 
     std::panic::set_hook(Box::new(move |panic_info| {
-        if let Ok(mut t) = crate::terminal::Terminal::new(4.0, 60.0, false, 50) {
-            if let Err(r) = t.exit() {
-                error!("Unable to exit Terminal: {:?}", r);
-            }
+        if let Ok(mut t) = crate::terminal::Terminal::new(
+            4.0,
+            60.0,
+            false,
+            50,
+            crate::terminal::TerminalOutput::stdout(),
+        ) && let Err(r) = t.exit()
+        {
+            error!("Unable to exit Terminal: {:?}", r);
         }
 
         #[cfg(not(debug_assertions))]
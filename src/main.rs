@@ -1,8 +1,15 @@
+use std::process::ExitCode;
+
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
 use color_eyre::Result;
 
-use crate::app::App;
+use crate::{
+    app::App,
+    config::{Config, check_config},
+    print::{print_paths, print_tree},
+    shell::{print_init, write_cwd},
+};
 
 mod action;
 mod app;
@@ -12,15 +19,44 @@ mod components;
 mod config;
 mod errors;
 mod logging;
+mod preview;
+mod print;
+mod shell;
 mod terminal;
+mod tree;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     crate::errors::init()?;
     crate::logging::init()?;
 
     let args = Cli::parse();
+    match &args.command {
+        Some(Command::Config { file, format }) => return check_config(file.as_deref(), *format),
+        Some(Command::Init { shell }) => {
+            print_init(*shell)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+    if let Some(format) = args.output_format() {
+        print_tree(&args.root()?, args.depth, format, &Config::new()?)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let null = args.null;
+    let cwd_file = args.cwd_file.clone();
     let mut app = App::new(args)?;
     app.run().await?;
-    Ok(())
+
+    if let (Some(cwd_file), Some(cwd)) = (cwd_file, app.cwd()) {
+        write_cwd(&cwd_file, cwd)?;
+    }
+    match app.picked() {
+        Some(paths) => print_paths(paths, null)?,
+        // nothing was picked before quitting
+        None if app.is_pick_mode() => return Ok(ExitCode::FAILURE),
+        None => {}
+    }
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,481 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use ratatui::prelude::*;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::tree::{
+    archive::{self, ArchiveFormat, ArchivedEntry},
+    details::{Column, Details, human_size},
+    node::{Node, NodeKind},
+};
+
+/// At most this much of a file is read, no matter how many lines are previewed.
+const MAX_BYTES: u64 = 64 * 1024;
+/// The number of bytes in a line of a hex dump.
+const HEX_WIDTH: usize = 16;
+const TAB: &str = "    ";
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// How the preview of the selected entry is shown beside the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewSettings {
+    /// whether the preview is shown when lsn starts
+    pub show: bool,
+    /// the share of the width of the view in percent
+    pub width: u16,
+    /// the number of lines of a file which are previewed
+    pub lines: usize,
+    /// the name of the syntax highlighting theme
+    pub theme: String,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            show: false,
+            width: 50,
+            lines: 200,
+            theme: "base16-ocean.dark".to_string(),
+        }
+    }
+}
+
+/// The names of the syntax highlighting themes which can be configured.
+pub fn theme_names() -> impl Iterator<Item = &'static str> {
+    THEMES.themes.keys().map(String::as_str)
+}
+
+/// The preview of a single entry, ready to be rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub path: PathBuf,
+    /// what the entry is, e.g. `Rust, 1.2K` or `directory, 12 entries`
+    pub summary: String,
+    pub lines: Vec<Line<'static>>,
+}
+
+/// Preview `node` on the blocking thread pool and hand the result to `emit`.
+///
+/// Nothing is emitted if `token` is cancelled before the preview is ready, e.g. because the
+/// selection moved on.
+pub fn preview_in_background(
+    node: Node,
+    settings: PreviewSettings,
+    token: CancellationToken,
+    emit: impl FnOnce(Preview) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let preview = read_preview(&node, &settings);
+        if !token.is_cancelled() {
+            emit(preview);
+        }
+    })
+}
+
+/// Preview `node`: the first lines of text files with syntax highlighting, a hex dump of
+/// binary files, the entries of directories and archives and the metadata of everything else.
+pub fn read_preview(node: &Node, settings: &PreviewSettings) -> Preview {
+    let path = node.path.as_path();
+    let (summary, lines) = match &node.kind {
+        NodeKind::Archive { .. } => preview_archive(path, None, settings.lines),
+        NodeKind::Archived { entry, .. } => preview_archived(entry, settings),
+        NodeKind::Directory { .. } | NodeKind::File => preview_path(path, settings),
+    };
+    Preview {
+        path: path.to_path_buf(),
+        summary,
+        lines,
+    }
+}
+
+fn preview_path(path: &Path, settings: &PreviewSettings) -> (String, Vec<Line<'static>>) {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => preview_dir(path, settings.lines),
+        Ok(metadata) if metadata.is_file() => preview_file(path, metadata.len(), settings),
+        Ok(_) => preview_special(path),
+        Err(_) if path.is_symlink() => {
+            let target = fs::read_link(path)
+                .map(|target| target.display().to_string())
+                .unwrap_or_default();
+            (
+                "broken symlink".to_string(),
+                vec![Line::from(format!("-> {target}"))],
+            )
+        }
+        Err(err) => error_preview(&err),
+    }
+}
+
+fn preview_file(path: &Path, len: u64, settings: &PreviewSettings) -> (String, Vec<Line<'static>>) {
+    let mut bytes = Vec::new();
+    match File::open(path).and_then(|file| file.take(MAX_BYTES).read_to_end(&mut bytes)) {
+        Ok(_) => preview_bytes(path, &bytes, len, settings),
+        Err(err) => error_preview(&err),
+    }
+}
+
+/// Preview the first bytes of a file of `len` bytes as text or as a hex dump.
+fn preview_bytes(
+    path: &Path,
+    bytes: &[u8],
+    len: u64,
+    settings: &PreviewSettings,
+) -> (String, Vec<Line<'static>>) {
+    if bytes.is_empty() {
+        return ("empty file".to_string(), Vec::new());
+    }
+
+    match text(bytes) {
+        Some(text) => {
+            let syntax = find_syntax(path, text);
+            let summary = format!("{}, {}", syntax.name, human_size(len));
+            (summary, highlight(text, syntax, settings))
+        }
+        None => (
+            format!("binary, {}", human_size(len)),
+            hex_dump(bytes, settings.lines),
+        ),
+    }
+}
+
+/// The bytes as text, unless they look like a binary file.
+fn text(bytes: &[u8]) -> Option<&str> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // the read may have stopped in the middle of a character
+        Err(err) if err.error_len().is_none() => {
+            Some(std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default())
+        }
+        Err(_) => None,
+    }
+}
+
+fn find_syntax(path: &Path, text: &str) -> &'static SyntaxReference {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let name = path.file_name().and_then(|name| name.to_str());
+    extension
+        .into_iter()
+        .chain(name)
+        .find_map(|token| SYNTAXES.find_syntax_by_extension(token))
+        .or_else(|| {
+            let first_line = text.lines().next().unwrap_or_default();
+            SYNTAXES.find_syntax_by_first_line(first_line)
+        })
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text())
+}
+
+fn highlight(
+    text: &str,
+    syntax: &SyntaxReference,
+    settings: &PreviewSettings,
+) -> Vec<Line<'static>> {
+    let theme = THEMES
+        .themes
+        .get(&settings.theme)
+        .or_else(|| THEMES.themes.get(&PreviewSettings::default().theme));
+    let mut highlighter = theme.map(|theme| HighlightLines::new(syntax, theme));
+
+    LinesWithEndings::from(text)
+        .take(settings.lines)
+        .map(|line| {
+            let ranges = highlighter
+                .as_mut()
+                .and_then(|highlighter| highlighter.highlight_line(line, &SYNTAXES).ok());
+            match ranges {
+                Some(ranges) => Line::from(
+                    ranges
+                        .into_iter()
+                        .map(|(style, text)| Span::styled(printable(text), convert_style(style)))
+                        .collect::<Vec<_>>(),
+                ),
+                None => Line::from(printable(line)),
+            }
+        })
+        .collect()
+}
+
+/// Only the foreground is taken from the theme, so the preview keeps the terminal background.
+fn convert_style(style: syntect::highlighting::Style) -> Style {
+    let color = style.foreground;
+    let mut converted = Style::new().fg(Color::Rgb(color.r, color.g, color.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        converted = converted.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        converted = converted.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        converted = converted.add_modifier(Modifier::UNDERLINED);
+    }
+    converted
+}
+
+/// Expand tabs and replace control characters, which would mess up the terminal.
+fn printable(text: &str) -> String {
+    text.trim_end_matches(['\n', '\r'])
+        .chars()
+        .map(|c| match c {
+            '\t' => TAB.to_string(),
+            c if c.is_control() => char::REPLACEMENT_CHARACTER.to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Dump the bytes like `hexdump -C` does, with the offset, the bytes and their characters.
+fn hex_dump(bytes: &[u8], lines: usize) -> Vec<Line<'static>> {
+    let dim = Style::new().fg(Color::DarkGray);
+    bytes
+        .chunks(HEX_WIDTH)
+        .take(lines)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut spans = vec![Span::styled(format!("{:08x}  ", index * HEX_WIDTH), dim)];
+            for column in 0..HEX_WIDTH {
+                let separator = match column {
+                    0 => "",
+                    column if column == HEX_WIDTH / 2 => "  ",
+                    _ => " ",
+                };
+                spans.push(Span::raw(separator));
+                spans.push(match chunk.get(column) {
+                    Some(byte) => Span::styled(format!("{byte:02x}"), byte_style(*byte)),
+                    None => Span::raw("  "),
+                });
+            }
+            spans.push(Span::styled("  |", dim));
+            spans.extend(chunk.iter().map(|byte| {
+                let c = if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                };
+                Span::styled(c.to_string(), byte_style(*byte))
+            }));
+            spans.push(Span::styled("|", dim));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn byte_style(byte: u8) -> Style {
+    match byte {
+        0 => Style::new().fg(Color::DarkGray),
+        byte if byte.is_ascii_graphic() => Style::new().fg(Color::Cyan),
+        byte if byte.is_ascii_whitespace() => Style::new().fg(Color::Green),
+        byte if byte.is_ascii() => Style::new().fg(Color::Magenta),
+        _ => Style::new().fg(Color::Yellow),
+    }
+}
+
+/// List the entries of the directory, directories first.
+fn preview_dir(path: &Path, lines: usize) -> (String, Vec<Line<'static>>) {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => return error_preview(&err),
+    };
+    let entries = entries
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let is_dir = entry.path().is_dir();
+            (!is_dir, entry.file_name().to_string_lossy().into_owned())
+        })
+        .collect();
+    listing("directory", entries, lines)
+}
+
+/// List the entries of an archive, or of the directory `inner` inside of it.
+fn preview_archive(
+    path: &Path,
+    inner: Option<&Path>,
+    lines: usize,
+) -> (String, Vec<Line<'static>>) {
+    let entries = match archive::list(path) {
+        Ok(entries) => entries,
+        Err(err) => return error_preview(&err),
+    };
+    let parent = inner.unwrap_or(Path::new(""));
+    let entries = entries
+        .into_iter()
+        .filter(|entry| entry.inner.parent() == Some(parent))
+        .map(|entry| {
+            let name = entry.inner.file_name().unwrap_or_default();
+            (!entry.is_dir, name.to_string_lossy().into_owned())
+        })
+        .collect();
+    let kind = match (inner, ArchiveFormat::of(path)) {
+        (None, Some(format)) => format!("{format} archive"),
+        _ => "directory".to_string(),
+    };
+    listing(&kind, entries, lines)
+}
+
+fn preview_archived(
+    entry: &ArchivedEntry,
+    settings: &PreviewSettings,
+) -> (String, Vec<Line<'static>>) {
+    if entry.is_dir {
+        return preview_archive(&entry.archive, Some(&entry.inner), settings.lines);
+    }
+    match archive::read_entry(&entry.archive, &entry.inner, MAX_BYTES) {
+        Ok(bytes) => preview_bytes(&entry.inner, &bytes, entry.size, settings),
+        Err(err) => error_preview(&err),
+    }
+}
+
+/// The names of the entries of a directory, directories first. `entries` holds whether an
+/// entry is not a directory together with its name.
+fn listing(
+    kind: &str,
+    mut entries: Vec<(bool, String)>,
+    lines: usize,
+) -> (String, Vec<Line<'static>>) {
+    entries.sort();
+    let summary = match entries.len() {
+        1 => format!("{kind}, 1 entry"),
+        count => format!("{kind}, {count} entries"),
+    };
+    let mut preview: Vec<Line> = entries
+        .iter()
+        .take(lines)
+        .map(|(is_file, name)| {
+            if *is_file {
+                Line::from(printable(name))
+            } else {
+                Line::styled(format!("{}/", printable(name)), Color::Blue)
+            }
+        })
+        .collect();
+    if entries.len() > lines {
+        preview.push(Line::from(format!("… {} more", entries.len() - lines)).dim());
+    }
+    (summary, preview)
+}
+
+/// Show the metadata of anything that is neither a file nor a directory, e.g. a socket.
+fn preview_special(path: &Path) -> (String, Vec<Line<'static>>) {
+    let node = Node::new(path, 0, None);
+    let details = Details::default();
+    let lines = [
+        Column::Permissions,
+        Column::Owner,
+        Column::Group,
+        Column::Mtime,
+    ]
+    .into_iter()
+    .map(|column| {
+        Line::from(vec![
+            Span::styled(format!("{column:<12}"), Style::new().fg(Color::DarkGray)),
+            Span::raw(details.format(column, &node)),
+        ])
+    })
+    .collect();
+    (special_kind(path).to_string(), lines)
+}
+
+#[cfg(unix)]
+fn special_kind(path: &Path) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+
+    match fs::metadata(path).map(|metadata| metadata.file_type()) {
+        Ok(file_type) if file_type.is_fifo() => "named pipe",
+        Ok(file_type) if file_type.is_socket() => "socket",
+        Ok(file_type) if file_type.is_block_device() => "block device",
+        Ok(file_type) if file_type.is_char_device() => "character device",
+        _ => "special file",
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_path: &Path) -> &'static str {
+    "special file"
+}
+
+fn error_preview(err: &io::Error) -> (String, Vec<Line<'static>>) {
+    (
+        "error".to_string(),
+        vec![Line::styled(err.to_string(), Color::Red)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn text_of(preview: &Preview) -> Vec<String> {
+        preview.lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_preview_text_and_binary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = PreviewSettings {
+            lines: 2,
+            ..PreviewSettings::default()
+        };
+
+        let source = dir.path().join("main.rs");
+        fs::write(&source, "fn main() {\n\tprintln!(\"\x1b\");\n}\n").unwrap();
+        let preview = read_preview(&Node::new(&source, 0, None), &settings);
+        assert_eq!(preview.summary, "Rust, 30");
+        assert_eq!(
+            text_of(&preview),
+            vec!["fn main() {", "    println!(\"\u{fffd}\");"]
+        );
+        assert!(preview.lines[0].spans.len() > 1);
+
+        let binary = dir.path().join("data.bin");
+        fs::write(&binary, b"\x7fELF\0\x01 text\n").unwrap();
+        let preview = read_preview(&Node::new(&binary, 0, None), &settings);
+        assert_eq!(preview.summary, "binary, 12");
+        assert_eq!(
+            text_of(&preview),
+            vec!["00000000  7f 45 4c 46 00 01 20 74  65 78 74 0a              |.ELF.. text.|"]
+        );
+
+        let empty = dir.path().join("empty");
+        fs::write(&empty, "").unwrap();
+        assert_eq!(
+            read_preview(&Node::new(&empty, 0, None), &settings).summary,
+            "empty file"
+        );
+    }
+
+    #[test]
+    fn test_preview_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "").unwrap();
+        fs::write(dir.path().join("b.txt"), "").unwrap();
+        fs::create_dir(dir.path().join("z")).unwrap();
+        let settings = PreviewSettings {
+            lines: 2,
+            ..PreviewSettings::default()
+        };
+
+        let preview = read_preview(&Node::new(dir.path(), 0, None), &settings);
+        assert_eq!(preview.summary, "directory, 3 entries");
+        assert_eq!(text_of(&preview), vec!["z/", "a.txt", "… 1 more"]);
+
+        let missing = read_preview(&Node::new(&dir.path().join("missing"), 0, None), &settings);
+        assert_eq!(missing.summary, "error");
+    }
+}
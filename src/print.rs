@@ -0,0 +1,441 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufWriter, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use crossterm::style::Stylize;
+
+use crate::{
+    arena::Handle,
+    cli::OutputFormat,
+    config::Config,
+    tree::{FileTree, filter::Filter, ignore::IgnoredEntries, node::Node},
+};
+
+/// Print the tree below `root` to stdout, either drawn like the `tree` command does
+/// or as JSON for other tools.
+///
+/// Directories are expanded up to `max_depth` levels below the root, or completely if no
+/// depth is given. The entries are sorted and filtered like in the interactive view, using
+/// the sort order, filters and ignored entries of `config`. Colors are only used if stdout is
+/// a terminal.
+pub fn print_tree(
+    root: &Path,
+    max_depth: Option<usize>,
+    format: OutputFormat,
+    config: &Config,
+) -> Result<()> {
+    let (tree, rows) = load_tree(root, max_depth, config)?;
+
+    let stdout = io::stdout();
+    let colored = stdout.is_terminal();
+    let mut out = BufWriter::new(stdout.lock());
+
+    let result = match format {
+        OutputFormat::Tree => write_tree(&mut out, &tree, &rows, colored),
+        OutputFormat::Json => write_json(&mut out, &tree, &rows),
+        OutputFormat::Ndjson => write_ndjson(&mut out, &tree, &rows),
+    };
+    match result.and_then(|_| out.flush()) {
+        // the reader went away, e.g. when piping into `head`
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Print the paths which were picked in the interactive view to stdout.
+///
+/// Every path is terminated by a newline or, if `null` is set, by a NUL character.
+pub fn print_paths(paths: &[PathBuf], null: bool) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    match write_paths(&mut out, paths, null).and_then(|_| out.flush()) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn write_paths(out: &mut impl Write, paths: &[PathBuf], null: bool) -> io::Result<()> {
+    let terminator = if null { b'\0' } else { b'\n' };
+    for path in paths {
+        out.write_all(path_bytes(path).as_ref())?;
+        out.write_all(&[terminator])?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+pub fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    path.to_string_lossy().into_owned().into_bytes().into()
+}
+
+/// Load the tree below `root` and return it together with the nodes which are shown, in the
+/// order they are printed.
+fn load_tree(
+    root: &Path,
+    max_depth: Option<usize>,
+    config: &Config,
+) -> io::Result<(FileTree, Vec<Handle>)> {
+    let mut tree = FileTree::new(root)?;
+    tree.set_sort_order(config.sort.clone());
+    tree.set_dir_sort_orders(config.dir_sorts.clone());
+    // the filter of the interactive view before anything is typed into it
+    let filter = Filter::new(config.filters.iter().cloned())
+        .hide_ignored(config.ignored == IgnoredEntries::Hide);
+    expand_all(&mut tree, max_depth, &filter);
+    let rows = tree.flatten_filtered(&filter);
+    Ok((tree, rows))
+}
+
+/// Load and open every directory of the tree up to `max_depth` levels below the root.
+///
+/// Directories which are hidden by `filter` are not read at all. Symlinks to directories are
+/// printed like files, as following them could lead into a loop.
+fn expand_all(tree: &mut FileTree, max_depth: Option<usize>, filter: &Filter) {
+    let Some(root) = tree.get(tree.root()).map(|root| root.path.clone()) else {
+        return;
+    };
+    let mut stack: Vec<Handle> = tree.children(tree.root()).collect();
+
+    while let Some(handle) = stack.pop() {
+        let Some(node) = tree.get(handle) else {
+            continue;
+        };
+        if !node.is_dir()
+            || node.symlink_target.is_some()
+            || max_depth.is_some_and(|max_depth| node.depth >= max_depth)
+        {
+            continue;
+        }
+        let relative = node.path.strip_prefix(&root).unwrap_or(&node.path);
+        if filter.excludes(node, relative) {
+            continue;
+        }
+
+        let path = node.path.clone();
+        if let Err(err) = tree.load_children(handle) {
+            eprintln!("lsn: {}: {}", path.display(), err);
+            continue;
+        }
+        tree.set_open(handle, true);
+        stack.extend(tree.children(handle));
+    }
+}
+
+fn write_tree(
+    out: &mut impl Write,
+    tree: &FileTree,
+    rows: &[Handle],
+    colored: bool,
+) -> io::Result<()> {
+    let (mut directories, mut files) = (0, 0);
+    let visible: HashSet<Handle> = rows.iter().copied().collect();
+
+    for &handle in rows {
+        let Some(node) = tree.get(handle) else {
+            continue;
+        };
+
+        if handle == tree.root() {
+            writeln!(
+                out,
+                "{}",
+                styled_name(node, node.path.display().to_string(), colored)
+            )?;
+            continue;
+        }
+
+        if node.is_dir() {
+            directories += 1;
+        } else {
+            files += 1;
+        }
+        writeln!(
+            out,
+            "{}{}",
+            connectors(tree, &visible, node),
+            styled_name(node, node.name().into_owned(), colored)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "\n{} {}, {} {}",
+        directories,
+        if directories == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+        files,
+        if files == 1 { "file" } else { "files" }
+    )
+}
+
+/// Write the nodes of `rows` as a single JSON array.
+fn write_json(out: &mut impl Write, tree: &FileTree, rows: &[Handle]) -> io::Result<()> {
+    let nodes: Vec<&Node> = rows.iter().filter_map(|handle| tree.get(*handle)).collect();
+    serde_json::to_writer_pretty(&mut *out, &nodes)?;
+    writeln!(out)
+}
+
+/// Write every node of `rows` as a JSON object on its own line.
+fn write_ndjson(out: &mut impl Write, tree: &FileTree, rows: &[Handle]) -> io::Result<()> {
+    for handle in rows {
+        if let Some(node) = tree.get(*handle) {
+            serde_json::to_writer(&mut *out, node)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Build the box drawing prefix of a node from the `visible` siblings of its ancestors.
+fn connectors(tree: &FileTree, visible: &HashSet<Handle>, node: &Node) -> String {
+    // siblings which are filtered out do not need a connector
+    let has_next = |node: &Node| {
+        let mut sibling = node.next_sibling;
+        while let Some(handle) = sibling {
+            if visible.contains(&handle) {
+                return true;
+            }
+            sibling = tree.get(handle).and_then(|node| node.next_sibling);
+        }
+        false
+    };
+    let mut parts = vec![if has_next(node) {
+        "├── "
+    } else {
+        "└── "
+    }];
+
+    let mut ancestor = node.parent.and_then(|parent| tree.get(parent));
+    while let Some(current) = ancestor {
+        // the root has no connector column of its own
+        let Some(parent) = current.parent else {
+            break;
+        };
+        parts.push(if has_next(current) { "│   " } else { "    " });
+        ancestor = tree.get(parent);
+    }
+
+    parts.reverse();
+    parts.concat()
+}
+
+fn styled_name(node: &Node, name: String, colored: bool) -> String {
+    if colored && node.is_dir() {
+        name.blue().bold().to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tree::{archive::ArchivedEntry, filter::Pattern, sort::SortOrder};
+
+    #[test]
+    fn test_write_tree() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("a/b"))?;
+        fs::write(dir.path().join("a/b/deep.txt"), "")?;
+        fs::write(dir.path().join("a/file.txt"), "")?;
+        fs::write(dir.path().join("z.txt"), "")?;
+
+        let mut tree = FileTree::new(dir.path())?;
+        expand_all(&mut tree, None, &Filter::default());
+        let mut out = Vec::new();
+        write_tree(&mut out, &tree, &tree.flatten(), false)?;
+
+        let expected = format!(
+            concat!(
+                "{}\n",
+                "├── a\n",
+                "│   ├── b\n",
+                "│   │   └── deep.txt\n",
+                "│   └── file.txt\n",
+                "└── z.txt\n",
+                "\n",
+                "2 directories, 3 files\n",
+            ),
+            dir.path().display()
+        );
+        assert_eq!(String::from_utf8(out)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_with_configured_sort_and_filters() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git2::Repository::init(dir.path())?;
+        fs::write(dir.path().join(".gitignore"), "target\n")?;
+        fs::create_dir_all(dir.path().join("target/debug"))?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src/a.rs"), "")?;
+        fs::write(dir.path().join("src/b.rs"), "")?;
+        fs::write(dir.path().join("src/build.log"), "")?;
+        let config = Config {
+            sort: SortOrder {
+                keys: vec!["-name".parse().unwrap()],
+                ..SortOrder::default()
+            },
+            filters: Pattern::parse_all("!*.log !.git").unwrap(),
+            ignored: IgnoredEntries::Hide,
+            ..Config::default()
+        };
+
+        let (tree, rows) = load_tree(dir.path(), None, &config)?;
+        let mut out = Vec::new();
+        write_tree(&mut out, &tree, &rows, false)?;
+
+        let expected = format!(
+            concat!(
+                "{}\n",
+                "├── src\n",
+                "│   ├── b.rs\n",
+                "│   └── a.rs\n",
+                "└── .gitignore\n",
+                "\n",
+                "1 directory, 3 files\n",
+            ),
+            dir.path().display()
+        );
+        assert_eq!(String::from_utf8(out)?, expected);
+        // the ignored directory is not even read
+        assert!(tree.find(&dir.path().join("target/debug")).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_symlinked_directories_are_not_expanded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("a"))?;
+        fs::write(dir.path().join("a/file.txt"), "")?;
+        std::os::unix::fs::symlink("..", dir.path().join("a/up"))?;
+        std::os::unix::fs::symlink("..", dir.path().join("a/up2"))?;
+
+        let (tree, rows) = load_tree(dir.path(), None, &Config::default())?;
+        let mut out = Vec::new();
+        write_tree(&mut out, &tree, &rows, false)?;
+
+        let expected = format!(
+            concat!(
+                "{}\n",
+                "└── a\n",
+                "    ├── up\n",
+                "    ├── up2\n",
+                "    └── file.txt\n",
+                "\n",
+                "3 directories, 1 file\n",
+            ),
+            dir.path().display()
+        );
+        assert_eq!(String::from_utf8(out)?, expected);
+        let up = tree.find(&dir.path().join("a/up")).unwrap();
+        assert_eq!(tree.children(up).count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_paths() -> Result<()> {
+        let paths = vec![PathBuf::from("/tmp/a b"), PathBuf::from("/tmp/c")];
+
+        let mut out = Vec::new();
+        write_paths(&mut out, &paths, false)?;
+        assert_eq!(String::from_utf8(out)?, "/tmp/a b\n/tmp/c\n");
+
+        let mut out = Vec::new();
+        write_paths(&mut out, &paths, true)?;
+        assert_eq!(String::from_utf8(out)?, "/tmp/a b\0/tmp/c\0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ndjson() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("a"))?;
+        fs::write(dir.path().join("a.txt"), "hello")?;
+        std::os::unix::fs::symlink("a.txt", dir.path().join("link"))?;
+        fs::write(dir.path().join("pkg.tar"), "")?;
+
+        let mut tree = FileTree::new(dir.path())?;
+        let archive = tree.find(&dir.path().join("pkg.tar")).unwrap();
+        tree.add_archived(
+            archive,
+            vec![ArchivedEntry {
+                archive: dir.path().join("pkg.tar"),
+                inner: PathBuf::from("tool"),
+                is_dir: false,
+                size: 42,
+                mtime: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+                mode: Some(0o100755),
+            }],
+        );
+        tree.set_open(archive, true);
+        let mut out = Vec::new();
+        write_ndjson(&mut out, &tree, &tree.flatten())?;
+
+        let records: Vec<serde_json::Value> = String::from_utf8(out)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        let summary: Vec<(&str, u64, &str)> = records
+            .iter()
+            .map(|record| {
+                (
+                    record["kind"].as_str().unwrap(),
+                    record["depth"].as_u64().unwrap(),
+                    record["symlink_target"].as_str().unwrap_or_default(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("directory", 0, ""),
+                ("directory", 1, ""),
+                ("file", 1, ""),
+                ("file", 1, "a.txt"),
+                ("file", 1, ""),
+                ("file", 2, ""),
+            ]
+        );
+        assert_eq!(records[2]["size"], 5);
+        assert!(records[2]["mtime"].is_string());
+        assert_eq!(records[5]["size"], 42);
+        assert_eq!(records[5]["mtime"], "2023-11-14T22:13:20Z");
+        assert_eq!(records[5]["permissions"], "0755");
+        Ok(())
+    }
+
+    #[test]
+    fn test_depth_limit() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("a/b"))?;
+
+        let mut tree = FileTree::new(dir.path())?;
+        expand_all(&mut tree, Some(1), &Filter::default());
+        let mut out = Vec::new();
+        write_tree(&mut out, &tree, &tree.flatten(), false)?;
+
+        assert!(String::from_utf8(out)?.ends_with("└── a\n\n1 directory, 0 files\n"));
+        Ok(())
+    }
+}
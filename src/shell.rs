@@ -0,0 +1,62 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use color_eyre::Result;
+
+use crate::{cli::Shell, print::path_bytes};
+
+/// The wrapper function for bash and zsh.
+///
+/// lsn writes the last browsed directory into a temporary file, which is read after lsn quit.
+const POSIX_INIT: &str = r#"lsn() {
+    local cwd_file cwd ret
+    cwd_file="$(mktemp -t lsn-cwd.XXXXXX)" || return
+    command lsn --cwd-file "$cwd_file" "$@"
+    ret=$?
+    cwd="$(cat -- "$cwd_file")"
+    rm -f -- "$cwd_file"
+    if [ -n "$cwd" ] && [ "$cwd" != "$PWD" ]; then
+        builtin cd -- "$cwd" || return
+    fi
+    return $ret
+}
+"#;
+
+const FISH_INIT: &str = r#"function lsn
+    set -l cwd_file (mktemp -t lsn-cwd.XXXXXX); or return
+    command lsn --cwd-file $cwd_file $argv
+    set -l ret $status
+    set -l cwd (cat -- $cwd_file)
+    rm -f -- $cwd_file
+    if test -n "$cwd"; and test "$cwd" != "$PWD"
+        builtin cd -- $cwd; or return
+    end
+    return $ret
+end
+"#;
+
+/// Print the shell function which wraps lsn and changes into the last browsed directory.
+pub fn print_init(shell: Shell) -> Result<()> {
+    let script = match shell {
+        Shell::Bash | Shell::Zsh => POSIX_INIT,
+        Shell::Fish => FISH_INIT,
+    };
+    io::stdout().write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Write `cwd` to `cwd_file`, or to stdout if it is `-`.
+pub fn write_cwd(cwd_file: &Path, cwd: &Path) -> Result<()> {
+    if cwd_file == Path::new("-") {
+        let mut stdout = io::stdout();
+        stdout.write_all(&path_bytes(cwd))?;
+        stdout.write_all(b"\n")?;
+        stdout.flush()?;
+    } else {
+        fs::write(cwd_file, path_bytes(cwd))?;
+    }
+    Ok(())
+}
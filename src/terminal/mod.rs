@@ -1,7 +1,8 @@
 pub mod events;
 
 use std::{
-    io::{Stdout, stdout},
+    fs::File,
+    io::{self, BufWriter, Stdout, Write, stdout},
     ops::{Deref, DerefMut},
     time::Duration,
 };
@@ -11,7 +12,7 @@ use crossterm::{
     cursor,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::backend::CrosstermBackend;
+use ratatui::{backend::CrosstermBackend, layout::Rect};
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     task::JoinHandle,
@@ -21,8 +22,42 @@ use tracing::error;
 
 use crate::terminal::events::TermEvent;
 
+/// Where the user interface is rendered to.
+pub enum TerminalOutput {
+    Stdout(Stdout),
+    /// the controlling terminal of the process, which keeps stdout free for other output
+    Tty(BufWriter<File>),
+}
+
+impl TerminalOutput {
+    pub fn stdout() -> Self {
+        Self::Stdout(stdout())
+    }
+
+    pub fn tty() -> io::Result<Self> {
+        let tty = File::options().write(true).open("/dev/tty")?;
+        Ok(Self::Tty(BufWriter::new(tty)))
+    }
+}
+
+impl Write for TerminalOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::Tty(tty) => tty.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::Tty(tty) => tty.flush(),
+        }
+    }
+}
+
 pub struct Terminal {
-    pub terminal: ratatui::Terminal<CrosstermBackend<Stdout>>,
+    pub terminal: ratatui::Terminal<CrosstermBackend<TerminalOutput>>,
     pub task: JoinHandle<()>,
     pub cancellation_token: CancellationToken,
     pub term_event_receiver: UnboundedReceiver<TermEvent>,
@@ -38,11 +73,12 @@ impl Terminal {
         frame_rate: f64,
         fullscreen: bool,
         inline_height: u16,
+        output: TerminalOutput,
     ) -> Result<Self> {
         let (sender, receiver) = mpsc::unbounded_channel();
 
         Ok(Self {
-            terminal: configure_terminal(fullscreen, inline_height)?,
+            terminal: configure_terminal(fullscreen, inline_height, output)?,
             task: tokio::spawn(async {}),
             cancellation_token: CancellationToken::new(),
             term_event_receiver: receiver,
@@ -56,7 +92,7 @@ impl Terminal {
     pub fn enter(&mut self) -> Result<()> {
         crossterm::terminal::enable_raw_mode()?;
         if self.fullscreen {
-            crossterm::execute!(stdout(), EnterAlternateScreen, cursor::Hide)?;
+            crossterm::execute!(self.backend_mut(), EnterAlternateScreen, cursor::Hide)?;
         }
         self.start_event_loop();
         Ok(())
@@ -67,13 +103,40 @@ impl Terminal {
         if crossterm::terminal::is_raw_mode_enabled()? {
             self.flush()?;
             if self.fullscreen {
-                crossterm::execute!(stdout(), LeaveAlternateScreen, cursor::Show)?;
+                crossterm::execute!(self.backend_mut(), LeaveAlternateScreen, cursor::Show)?;
             }
             crossterm::terminal::disable_raw_mode()?;
         }
         Ok(())
     }
 
+    /// Restore the terminal and stop the process with `SIGTSTP`.
+    ///
+    /// This returns as soon as the process is continued again (e.g. by `fg` in the shell),
+    /// afterwards [`Terminal::resume`] has to be called to take over the terminal again.
+    pub fn suspend(&mut self) -> Result<()> {
+        if !self.fullscreen {
+            // move the cursor to the top of the inline viewport, so the shell
+            // continues where the viewport started
+            self.clear()?;
+        }
+        self.show_cursor()?;
+        self.exit()?;
+        #[cfg(not(windows))]
+        signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?;
+        Ok(())
+    }
+
+    /// Take over the terminal again after a [`Terminal::suspend`].
+    ///
+    /// The viewport is recalculated and cleared, so the next draw renders the whole frame.
+    pub fn resume(&mut self) -> Result<()> {
+        self.enter()?;
+        let size = self.size()?;
+        self.resize(Rect::new(0, 0, size.width, size.height))?;
+        Ok(())
+    }
+
     pub fn start_event_loop(&mut self) {
         self.cancellation_token.cancel();
         self.cancellation_token = CancellationToken::new();
@@ -111,7 +174,7 @@ impl Terminal {
 }
 
 impl Deref for Terminal {
-    type Target = ratatui::Terminal<CrosstermBackend<Stdout>>;
+    type Target = ratatui::Terminal<CrosstermBackend<TerminalOutput>>;
 
     fn deref(&self) -> &Self::Target {
         &self.terminal
@@ -135,14 +198,21 @@ impl Drop for Terminal {
 fn configure_terminal(
     fullscreen: bool,
     inline_height: u16,
-) -> Result<ratatui::Terminal<CrosstermBackend<Stdout>>> {
+    output: TerminalOutput,
+) -> Result<ratatui::Terminal<CrosstermBackend<TerminalOutput>>> {
+    let backend = CrosstermBackend::new(output);
     if fullscreen {
-        let terminal = ratatui::Terminal::new(CrosstermBackend::new(stdout()))?;
+        let terminal = ratatui::Terminal::new(backend)?;
         Ok(terminal)
     } else {
-        let terminal = ratatui::init_with_options(ratatui::TerminalOptions {
-            viewport: ratatui::Viewport::Inline(inline_height),
-        });
+        // the same as `ratatui::init_with_options`, but that always renders to stdout
+        crossterm::terminal::enable_raw_mode()?;
+        let terminal = ratatui::Terminal::with_options(
+            backend,
+            ratatui::TerminalOptions {
+                viewport: ratatui::Viewport::Inline(inline_height),
+            },
+        )?;
         Ok(terminal)
     }
 }
@@ -0,0 +1,486 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use flate2::read::GzDecoder;
+use strum::Display;
+use time::{Date, Month, PrimitiveDateTime, Time};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use zip::ZipArchive;
+
+/// The kinds of archives which can be browsed like directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ArchiveFormat {
+    Tar,
+    #[strum(serialize = "tar.gz")]
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// The format of the archive at `path` by its file name, e.g. `release.tar.gz`.
+    pub fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// An entry inside of an archive, as it is described by the headers of the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedEntry {
+    /// the archive on disk which contains the entry
+    pub archive: PathBuf,
+    /// the path of the entry relative to the root of the archive
+    pub inner: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub mtime: Option<SystemTime>,
+    /// the unix mode, if the archive stores one
+    pub mode: Option<u32>,
+}
+
+/// List all entries of the archive at `path`.
+///
+/// Directories which are only implied by the paths of their entries are listed as well.
+/// Entries whose paths would lead out of the archive, e.g. `../escape`, are left out.
+pub fn list(path: &Path) -> io::Result<Vec<ArchivedEntry>> {
+    let mut entries = BTreeMap::new();
+    visit(path, |entry, _| {
+        entries.insert(entry.inner.clone(), entry);
+        Ok(true)
+    })?;
+
+    let implied: Vec<PathBuf> = entries
+        .keys()
+        .flat_map(|inner| inner.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty() && !entries.contains_key(*dir))
+        .map(Path::to_path_buf)
+        .collect();
+    for inner in implied {
+        entries.insert(
+            inner.clone(),
+            ArchivedEntry {
+                archive: path.to_path_buf(),
+                inner,
+                is_dir: true,
+                size: 0,
+                mtime: None,
+                mode: None,
+            },
+        );
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// Read the first `limit` bytes of the file `inner` in the archive at `path`.
+pub fn read_entry(path: &Path, inner: &Path, limit: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = None;
+    visit(path, |entry, reader| {
+        if entry.inner != inner || entry.is_dir {
+            return Ok(true);
+        }
+        let mut content = Vec::new();
+        reader.take(limit).read_to_end(&mut content)?;
+        bytes = Some(content);
+        Ok(false)
+    })?;
+    bytes.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not in the archive", inner.display()),
+        )
+    })
+}
+
+/// Extract the entry `inner` of the archive at `path` with everything below it into `dest`,
+/// or the whole archive if there is no `inner`. Returns the number of extracted entries.
+///
+/// The entry keeps its name, e.g. extracting `pkg/src` to `out` creates `out/src`. Existing
+/// files are never overwritten, and only directories and regular files are extracted. If
+/// any of the files exists already, nothing is extracted at all.
+pub fn extract(path: &Path, inner: Option<&Path>, dest: &Path) -> io::Result<usize> {
+    let base = inner
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let target_of = |entry: &ArchivedEntry| {
+        if inner.is_some_and(|inner| !entry.inner.starts_with(inner)) {
+            return None;
+        }
+        let relative = entry.inner.strip_prefix(&base).ok()?;
+        Some(dest.join(relative))
+    };
+
+    // a conflict is found before anything is written, so no half extracted tree is left
+    visit(path, |entry, reader| {
+        let Some(target) = target_of(&entry) else {
+            return Ok(true);
+        };
+        let blocked = if entry.is_dir {
+            target.exists() && !target.is_dir()
+        } else {
+            reader.regular_file().is_some() && target.symlink_metadata().is_ok()
+        };
+        if blocked {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", target.display()),
+            ));
+        }
+        Ok(true)
+    })?;
+
+    let mut extracted = 0;
+    visit(path, |entry, reader| {
+        let Some(target) = target_of(&entry) else {
+            return Ok(true);
+        };
+
+        if entry.is_dir {
+            fs::create_dir_all(&target)?;
+        } else if let Some(reader) = reader.regular_file() {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = File::create_new(&target).map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {}", target.display(), err))
+            })?;
+            io::copy(reader, &mut file)?;
+            set_mode(&target, entry.mode)?;
+        } else {
+            return Ok(true);
+        }
+        extracted += 1;
+        Ok(true)
+    })?;
+    Ok(extracted)
+}
+
+/// List the entries of the archive at `path` on the blocking thread pool and hand them to
+/// `emit`, unless `token` is cancelled before.
+pub fn list_in_background(
+    path: PathBuf,
+    token: CancellationToken,
+    emit: impl FnOnce(io::Result<Vec<ArchivedEntry>>) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let entries = list(&path);
+        if !token.is_cancelled() {
+            emit(entries);
+        }
+    })
+}
+
+/// Run [`extract`] on the blocking thread pool and hand the result to `emit`.
+pub fn extract_in_background(
+    path: PathBuf,
+    inner: Option<PathBuf>,
+    dest: PathBuf,
+    emit: impl FnOnce(io::Result<usize>) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || emit(extract(&path, inner.as_deref(), &dest)))
+}
+
+/// The content of an entry while its archive is visited.
+struct EntryReader<'a> {
+    reader: &'a mut dyn Read,
+    is_file: bool,
+}
+
+impl EntryReader<'_> {
+    /// The content of the entry if it is a regular file, and not e.g. a symlink.
+    fn regular_file(&mut self) -> Option<&mut dyn Read> {
+        self.is_file.then_some(&mut *self.reader)
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// Call `visit` for every entry of the archive at `path` in the order of the archive, until
+/// it returns `false`.
+fn visit(
+    path: &Path,
+    mut visit: impl FnMut(ArchivedEntry, &mut EntryReader) -> io::Result<bool>,
+) -> io::Result<()> {
+    let format = ArchiveFormat::of(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} is not a known archive", path.display()),
+        )
+    })?;
+    let file = BufReader::new(File::open(path)?);
+    match format {
+        ArchiveFormat::Tar => visit_tar(path, file, visit),
+        ArchiveFormat::TarGz => visit_tar(path, GzDecoder::new(file), visit),
+        ArchiveFormat::Zip => {
+            let mut archive = ZipArchive::new(file).map_err(io::Error::other)?;
+            for index in 0..archive.len() {
+                let mut file = archive.by_index(index).map_err(io::Error::other)?;
+                let Some(inner) = file.enclosed_name().and_then(|inner| enclosed(&inner)) else {
+                    continue;
+                };
+                let entry = ArchivedEntry {
+                    archive: path.to_path_buf(),
+                    inner,
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    mtime: file.last_modified().and_then(|mtime| {
+                        zip_time(
+                            mtime.year(),
+                            mtime.month(),
+                            mtime.day(),
+                            mtime.hour(),
+                            mtime.minute(),
+                            mtime.second(),
+                        )
+                    }),
+                    mode: file.unix_mode(),
+                };
+                let is_file = file.is_file();
+                let mut reader = EntryReader {
+                    reader: &mut file,
+                    is_file,
+                };
+                if !visit(entry, &mut reader)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn visit_tar(
+    path: &Path,
+    reader: impl Read,
+    mut visit: impl FnMut(ArchivedEntry, &mut EntryReader) -> io::Result<bool>,
+) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let Some(inner) = enclosed(&entry.path()?) else {
+            continue;
+        };
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let archived = ArchivedEntry {
+            archive: path.to_path_buf(),
+            inner,
+            is_dir: entry_type.is_dir(),
+            size: entry.size(),
+            mtime: header
+                .mtime()
+                .ok()
+                .map(|mtime| SystemTime::UNIX_EPOCH + Duration::from_secs(mtime)),
+            mode: header.mode().ok(),
+        };
+        let mut reader = EntryReader {
+            reader: &mut entry,
+            is_file: entry_type.is_file(),
+        };
+        if !visit(archived, &mut reader)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The path of an entry without a leading `./`, or `None` if it would lead out of the archive.
+fn enclosed(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => enclosed.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!enclosed.as_os_str().is_empty()).then_some(enclosed)
+}
+
+/// Zip archives store the local time without a time zone, so it is taken as UTC.
+fn zip_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<SystemTime> {
+    let date = Date::from_calendar_date(year.into(), Month::try_from(month).ok()?, day).ok()?;
+    let time = Time::from_hms(hour, minute, second).ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc().into())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+    use pretty_assertions::assert_eq;
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    /// A `.tar.gz` with `pkg/bin/tool` and `pkg/README`, but without entries for the
+    /// directories.
+    fn tar_gz(path: &Path) {
+        let encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content, mode) in [
+            ("pkg/bin/tool", "#!/bin/sh\n", 0o755),
+            ("pkg/README", "read me\n", 0o644),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(mode);
+            header.set_mtime(1_700_000_000);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_list_and_read_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("release.tar.gz");
+        tar_gz(&path);
+
+        let entries = list(&path).unwrap();
+        let inner: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.inner.to_string_lossy().into_owned(), entry.is_dir))
+            .collect();
+        assert_eq!(
+            inner,
+            vec![
+                ("pkg".to_string(), true),
+                ("pkg/README".to_string(), false),
+                ("pkg/bin".to_string(), true),
+                ("pkg/bin/tool".to_string(), false),
+            ]
+        );
+        assert_eq!(entries[1].size, 8);
+        assert_eq!(
+            entries[1].mtime,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+
+        assert_eq!(
+            read_entry(&path, Path::new("pkg/README"), 4).unwrap(),
+            b"read"
+        );
+        assert!(read_entry(&path, Path::new("pkg/missing"), 4).is_err());
+    }
+
+    #[test]
+    fn test_extract_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("release.tgz");
+        tar_gz(&path);
+
+        let out = dir.path().join("out");
+        assert_eq!(extract(&path, Some(Path::new("pkg/bin")), &out).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(out.join("bin").join("tool")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(out.join("bin").join("tool"))
+                .unwrap()
+                .permissions();
+            assert_eq!(mode.mode() & 0o777, 0o755);
+        }
+        // existing files are not overwritten
+        assert!(extract(&path, Some(Path::new("pkg/bin")), &out).is_err());
+
+        assert_eq!(extract(&path, None, &out).unwrap(), 2);
+        assert!(out.join("pkg").join("README").is_file());
+    }
+
+    #[test]
+    fn test_extract_nothing_if_a_file_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("release.tgz");
+        tar_gz(&path);
+        let out = dir.path().join("out");
+        fs::create_dir_all(out.join("pkg")).unwrap();
+        fs::write(out.join("pkg").join("README"), "mine\n").unwrap();
+
+        let err = extract(&path, None, &out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{} already exists",
+                out.join("pkg").join("README").display()
+            )
+        );
+        // the tool comes before the README in the archive, but is not extracted either
+        assert!(!out.join("pkg").join("bin").exists());
+        assert_eq!(
+            fs::read_to_string(out.join("pkg").join("README")).unwrap(),
+            "mine\n"
+        );
+    }
+
+    #[test]
+    fn test_list_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("docs.ZIP");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer
+            .add_directory("docs/", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .start_file("docs/guide.md", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"# Guide\n").unwrap();
+        writer
+            .start_file("../escape", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(ArchiveFormat::of(&path), Some(ArchiveFormat::Zip));
+        let entries = list(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].inner, Path::new("docs/guide.md"));
+        assert_eq!(entries[1].size, 8);
+        assert_eq!(
+            read_entry(&path, Path::new("docs/guide.md"), 100).unwrap(),
+            b"# Guide\n"
+        );
+    }
+}
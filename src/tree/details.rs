@@ -0,0 +1,363 @@
+use std::{cell::RefCell, collections::HashMap, fs::Metadata, time::SystemTime};
+
+use strum::{Display, EnumString};
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
+
+use crate::tree::{archive::ArchivedEntry, node::Node};
+
+/// A detail of an entry which can be shown in a column beside the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Column {
+    /// the size in a human readable form, e.g. `1.2K`, or the measured disk usage of a
+    /// directory
+    Size,
+    /// the time of the last modification in the local time zone
+    Mtime,
+    /// the unix mode string, e.g. `drwxr-xr-x`
+    Permissions,
+    Owner,
+    Group,
+    /// the number of hard links
+    Links,
+}
+
+impl Column {
+    /// The width of the column if none is configured.
+    pub fn default_width(self) -> u16 {
+        match self {
+            Column::Size => 5,
+            Column::Mtime => 16,
+            Column::Permissions => 10,
+            Column::Owner | Column::Group => 8,
+            Column::Links => 3,
+        }
+    }
+}
+
+/// Which columns are shown, in which order and how wide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Columns {
+    /// whether the columns are shown when lsn starts
+    pub show: bool,
+    pub order: Vec<Column>,
+    /// the widths of the columns which differ from their default width
+    pub widths: HashMap<Column, u16>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            show: false,
+            order: vec![
+                Column::Permissions,
+                Column::Links,
+                Column::Owner,
+                Column::Group,
+                Column::Size,
+                Column::Mtime,
+            ],
+            widths: HashMap::new(),
+        }
+    }
+}
+
+impl Columns {
+    pub fn width(&self, column: Column) -> u16 {
+        self.widths
+            .get(&column)
+            .copied()
+            .unwrap_or_else(|| column.default_width())
+    }
+}
+
+/// Formats the details of entries, remembering the names of users and groups.
+#[derive(Default)]
+pub struct Details {
+    users: RefCell<HashMap<u32, String>>,
+    groups: RefCell<HashMap<u32, String>>,
+}
+
+impl Details {
+    /// The value of `column` for `node`, or `-` if it is unknown.
+    pub fn format(&self, column: Column, node: &Node) -> String {
+        if let Some(entry) = node.archived_entry() {
+            return format_archived(column, entry).unwrap_or_else(|| "-".to_string());
+        }
+        let Some(metadata) = &node.metadata else {
+            return "-".to_string();
+        };
+        let value = match column {
+            // the size of a directory itself says nothing about its contents
+            Column::Size if node.is_dir() => node.usage.map(|usage| human_size(usage.bytes)),
+            Column::Size => Some(human_size(metadata.len())),
+            Column::Mtime => metadata.modified().ok().map(format_mtime),
+            Column::Permissions => mode_string(metadata),
+            Column::Owner => owner(metadata).map(|uid| {
+                self.users
+                    .borrow_mut()
+                    .entry(uid)
+                    .or_insert_with(|| user_name(uid).unwrap_or_else(|| uid.to_string()))
+                    .clone()
+            }),
+            Column::Group => group(metadata).map(|gid| {
+                self.groups
+                    .borrow_mut()
+                    .entry(gid)
+                    .or_insert_with(|| group_name(gid).unwrap_or_else(|| gid.to_string()))
+                    .clone()
+            }),
+            Column::Links => links(metadata).map(|links| links.to_string()),
+        };
+        value.unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// The details of an entry of an archive, which are taken from the headers of the archive.
+fn format_archived(column: Column, entry: &ArchivedEntry) -> Option<String> {
+    match column {
+        Column::Size if entry.is_dir => None,
+        Column::Size => Some(human_size(entry.size)),
+        Column::Mtime => entry.mtime.map(format_mtime),
+        Column::Permissions => entry
+            .mode
+            .map(|mode| permissions(if entry.is_dir { 'd' } else { '-' }, mode)),
+        Column::Owner | Column::Group | Column::Links => None,
+    }
+}
+
+fn format_mtime(mtime: SystemTime) -> String {
+    let mtime = OffsetDateTime::from(mtime);
+    mtime
+        .to_offset(local_offset(mtime))
+        .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+        .unwrap_or_default()
+}
+
+/// Format `bytes` like `ls -h` does, with a single decimal for small values.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1}{}", size, UNITS[unit])
+    } else {
+        format!("{:.0}{}", size, UNITS[unit])
+    }
+}
+
+/// The offset of the local time zone at `time`.
+///
+/// The `time` crate refuses to determine it once threads are running, so it is asked from
+/// the C library.
+#[cfg(unix)]
+fn local_offset(time: OffsetDateTime) -> UtcOffset {
+    let timestamp = time.unix_timestamp() as libc::time_t;
+    // SAFETY: localtime_r only writes to the given `tm`, which is zeroed and owned by us
+    let offset = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&timestamp, &mut tm).is_null() {
+            return UtcOffset::UTC;
+        }
+        tm.tm_gmtoff
+    };
+    UtcOffset::from_whole_seconds(offset as i32).unwrap_or(UtcOffset::UTC)
+}
+
+#[cfg(not(unix))]
+fn local_offset(_time: OffsetDateTime) -> UtcOffset {
+    UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
+}
+
+#[cfg(unix)]
+fn mode_string(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else {
+        '-'
+    };
+
+    Some(permissions(kind, metadata.permissions().mode()))
+}
+
+#[cfg(not(unix))]
+fn mode_string(_metadata: &Metadata) -> Option<String> {
+    None
+}
+
+/// The unix mode string of an entry of the given `kind`, e.g. `drwxr-xr-x` for `d`.
+fn permissions(kind: char, mode: u32) -> String {
+    let mut string = String::from(kind);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        string.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        string.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        string.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    string
+}
+
+#[cfg(unix)]
+fn owner(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn group(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.gid())
+}
+
+#[cfg(not(unix))]
+fn group(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn links(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn links(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: getpwuid_r only writes into `passwd` and `buffer`, whose size is passed along,
+    // and `name` points into `buffer` if an entry was found
+    unsafe {
+        let mut passwd: libc::passwd = std::mem::zeroed();
+        let mut result = std::ptr::null_mut();
+        libc::getpwuid_r(
+            uid,
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+        if result.is_null() {
+            return None;
+        }
+        Some(
+            std::ffi::CStr::from_ptr(passwd.pw_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(not(unix))]
+fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: getgrgid_r only writes into `group` and `buffer`, whose size is passed along,
+    // and `name` points into `buffer` if an entry was found
+    unsafe {
+        let mut group: libc::group = std::mem::zeroed();
+        let mut result = std::ptr::null_mut();
+        libc::getgrgid_r(
+            gid,
+            &mut group,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+        if result.is_null() {
+            return None;
+        }
+        Some(
+            std::ffi::CStr::from_ptr(group.gr_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(not(unix))]
+fn group_name(_gid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(20 * 1024 * 1024), "20M");
+        assert_eq!(human_size(u64::MAX), "16E");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_details() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "echo").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o4754)).unwrap();
+        let node = Node::new(&path, 1, None);
+
+        let details = Details::default();
+        assert_eq!(details.format(Column::Size, &node), "4");
+        assert_eq!(details.format(Column::Permissions, &node), "-rwsr-xr--");
+        assert_eq!(details.format(Column::Links, &node), "1");
+        assert_eq!(details.format(Column::Mtime, &node).len(), 16);
+        assert_ne!(details.format(Column::Owner, &node), "-");
+
+        let dir_node = Node::new(dir.path(), 0, None);
+        assert_eq!(details.format(Column::Size, &dir_node), "-");
+        assert!(
+            details
+                .format(Column::Permissions, &dir_node)
+                .starts_with('d')
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::{fmt, path::Path};
+
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use regex::Regex;
+
+use crate::tree::node::Node;
+
+/// A single filter pattern as it is written in the config or typed into the view.
+///
+/// - `*.rs` is a glob which is matched against the name of an entry, or against its path
+///   relative to the root if the glob contains a `/`, e.g. `src/**/*.rs`
+/// - `/^mod\.rs$/` is a regex which is matched against the name of an entry
+/// - a leading `!` turns either of them into an exclude, e.g. `!target` or `!/^\./`
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    exclude: bool,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Name(GlobMatcher),
+    Path(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let (exclude, pattern) = match source.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, source),
+        };
+        if pattern.is_empty() {
+            return Err(format!("empty pattern `{source}`"));
+        }
+
+        let matcher = if let Some(regex) = pattern
+            .strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'))
+        {
+            Matcher::Regex(Regex::new(regex).map_err(|err| err.to_string())?)
+        } else if pattern.contains('/') {
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .literal_separator(true)
+                .build()
+                .map_err(|err| err.to_string())?;
+            Matcher::Path(glob.compile_matcher())
+        } else {
+            let glob = Glob::new(pattern).map_err(|err| err.to_string())?;
+            Matcher::Name(glob.compile_matcher())
+        };
+
+        Ok(Self {
+            source: source.to_string(),
+            exclude,
+            matcher,
+        })
+    }
+
+    /// Parse all whitespace separated patterns of `line`.
+    pub fn parse_all(line: &str) -> Result<Vec<Self>, String> {
+        line.split_whitespace().map(Self::parse).collect()
+    }
+
+    fn is_match(&self, node: &Node, relative: &Path) -> bool {
+        match &self.matcher {
+            Matcher::Name(glob) => glob.is_match(node.name().as_ref()),
+            Matcher::Path(glob) => glob.is_match(relative),
+            Matcher::Regex(regex) => regex.is_match(&node.name()),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Decides which entries of the tree are shown.
+///
+/// An entry is hidden together with everything below it if it matches any exclude or if it
+/// is ignored while ignored entries are hidden. If there are includes, an entry is only shown
+/// if it or one of its ancestors matches one of them, or if it is a directory with a shown
+/// entry below it.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+    hide_ignored: bool,
+}
+
+impl Filter {
+    pub fn new(patterns: impl IntoIterator<Item = Pattern>) -> Self {
+        let (excludes, includes) = patterns.into_iter().partition(|pattern| pattern.exclude);
+        Self {
+            includes,
+            excludes,
+            hide_ignored: false,
+        }
+    }
+
+    /// Also hide the entries which are ignored, see [`Node::ignored`].
+    pub fn hide_ignored(mut self, hide: bool) -> Self {
+        self.hide_ignored = hide;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.includes.is_empty() && self.excludes.is_empty() && !self.hide_ignored
+    }
+
+    pub fn has_includes(&self) -> bool {
+        !self.includes.is_empty()
+    }
+
+    /// Whether `node` at `relative` to the root is hidden with everything below it.
+    pub fn excludes(&self, node: &Node, relative: &Path) -> bool {
+        (self.hide_ignored && node.ignored)
+            || self
+                .excludes
+                .iter()
+                .any(|pattern| pattern.is_match(node, relative))
+    }
+
+    /// Whether `node` at `relative` to the root matches one of the includes.
+    pub fn includes(&self, node: &Node, relative: &Path) -> bool {
+        self.includes
+            .iter()
+            .any(|pattern| pattern.is_match(node, relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src").join("main.rs"), "").unwrap();
+        let node = Node::new(&dir.path().join("src").join("main.rs"), 2, None);
+        let relative = Path::new("src/main.rs");
+
+        let matches = |source: &str| Pattern::parse(source).unwrap().is_match(&node, relative);
+        assert!(matches("*.rs"));
+        assert!(matches("!*.rs"));
+        assert!(matches("src/*.rs"));
+        assert!(matches("/src/*.rs"));
+        assert!(!matches("*/*/*.rs"));
+        assert!(matches("/^ma.n/"));
+        assert!(!matches("/^src/"));
+
+        assert!(Pattern::parse("!*.rs").unwrap().exclude);
+        assert!(Pattern::parse("!").is_err());
+        assert!(Pattern::parse("/(/").is_err());
+        assert!(Pattern::parse("a[").is_err());
+        assert_eq!(
+            Pattern::parse_all(" *.rs  !target ")
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["*.rs", "!target"]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use git2::{Repository, Status, StatusOptions};
+use strum::Display;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// The git status of an entry, ordered by how much attention it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    /// changed in the index, but not in the working tree
+    Staged,
+    /// changed in the working tree, which includes deleted and renamed entries
+    Modified,
+    /// unmerged after a merge or rebase
+    Conflicted,
+}
+
+impl GitStatus {
+    fn from_git(status: Status) -> Option<Self> {
+        if status.is_conflicted() {
+            Some(GitStatus::Conflicted)
+        } else if status.intersects(
+            Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE,
+        ) {
+            Some(GitStatus::Modified)
+        } else if status.intersects(
+            Status::INDEX_NEW
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_DELETED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE,
+        ) {
+            Some(GitStatus::Staged)
+        } else if status.is_wt_new() {
+            Some(GitStatus::Untracked)
+        } else if status.is_ignored() {
+            Some(GitStatus::Ignored)
+        } else {
+            None
+        }
+    }
+}
+
+/// The git status of the entries of the repository which contains a directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitStatuses {
+    /// the `.git` directory, which changes when entries are staged or committed
+    pub git_dir: PathBuf,
+    /// the root of the working tree
+    workdir: PathBuf,
+    /// the entries which git reported, by their absolute path
+    entries: HashMap<PathBuf, GitStatus>,
+    /// the untracked and ignored directories, whose entries git does not report one by one
+    whole_dirs: HashMap<PathBuf, GitStatus>,
+    /// the status which needs the most attention among the entries below a directory
+    below: HashMap<PathBuf, GitStatus>,
+}
+
+impl GitStatuses {
+    /// Read the status of the entries below `dir` from the repository which contains it.
+    ///
+    /// Returns `None` if `dir` is not inside of a working tree or the repository cannot be
+    /// read.
+    pub fn read(dir: &Path) -> Option<Self> {
+        let repo = Repository::discover(dir).ok()?;
+        let workdir = repo.workdir()?.to_path_buf();
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .include_ignored(true)
+            .recurse_untracked_dirs(false)
+            .recurse_ignored_dirs(false);
+        if let Ok(relative) = dir.strip_prefix(&workdir)
+            && !relative.as_os_str().is_empty()
+        {
+            options.pathspec(relative);
+        }
+        let git_statuses = repo.statuses(Some(&mut options)).ok()?;
+
+        let mut statuses = GitStatuses {
+            git_dir: repo.path().to_path_buf(),
+            workdir: workdir.clone(),
+            ..GitStatuses::default()
+        };
+        for entry in git_statuses.iter() {
+            let (Some(path), Some(status)) = (entry.path(), GitStatus::from_git(entry.status()))
+            else {
+                continue;
+            };
+            // directories are only reported as a whole if all of their entries are untracked
+            // or ignored, which is marked by a trailing slash
+            let is_whole_dir = path.ends_with('/');
+            let path = workdir.join(path.trim_end_matches('/'));
+            if status != GitStatus::Ignored {
+                statuses.add_below(&workdir, &path, status);
+            }
+            if is_whole_dir {
+                statuses.whole_dirs.insert(path.clone(), status);
+            }
+            statuses.entries.insert(path, status);
+        }
+        Some(statuses)
+    }
+
+    /// The status of the entry at `path`, or of the entries below it for directories.
+    pub fn get(&self, path: &Path) -> Option<GitStatus> {
+        if let Some(status) = self.entries.get(path).or_else(|| self.below.get(path)) {
+            return Some(*status);
+        }
+        path.ancestors()
+            .skip(1)
+            .find_map(|ancestor| self.whole_dirs.get(ancestor))
+            .copied()
+    }
+
+    /// Whether a change of the entries of `dir` can change the statuses, i.e. whether it is
+    /// the git directory or inside of the working tree without being ignored.
+    pub fn is_affected_by(&self, dir: &Path) -> bool {
+        dir.starts_with(&self.git_dir)
+            || (dir.starts_with(&self.workdir) && self.get(dir) != Some(GitStatus::Ignored))
+    }
+
+    /// Let the directories between `workdir` and `path` know about the status of `path`.
+    fn add_below(&mut self, workdir: &Path, path: &Path, status: GitStatus) {
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(workdir) {
+                break;
+            }
+            let below = self.below.entry(dir.to_path_buf()).or_insert(status);
+            *below = (*below).max(status);
+        }
+    }
+}
+
+/// Read the git status of the entries below `dir` on the blocking thread pool and hand it
+/// to `emit`, unless `token` is cancelled before it is read.
+pub fn read_in_background(
+    dir: PathBuf,
+    token: CancellationToken,
+    emit: impl FnOnce(Option<GitStatuses>) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let statuses = GitStatuses::read(&dir);
+        if !token.is_cancelled() {
+            emit(statuses);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_read_statuses() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("src").join("deep")).unwrap();
+        fs::create_dir(root.join("target")).unwrap();
+        fs::create_dir(root.join("new")).unwrap();
+        fs::write(root.join(".gitignore"), "target\n").unwrap();
+        fs::write(root.join("src").join("lib.rs"), "").unwrap();
+        fs::write(root.join("src").join("deep").join("main.rs"), "").unwrap();
+        fs::write(root.join("target").join("out"), "").unwrap();
+        fs::write(root.join("new").join("file"), "").unwrap();
+
+        let mut index = repo.index().unwrap();
+        for path in [".gitignore", "src/lib.rs", "src/deep/main.rs"] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("lsn", "lsn@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        fs::write(
+            root.join("src").join("deep").join("main.rs"),
+            "fn main() {}",
+        )
+        .unwrap();
+        fs::write(root.join("src").join("staged.rs"), "").unwrap();
+        index.add_path(Path::new("src/staged.rs")).unwrap();
+        index.write().unwrap();
+
+        let statuses = GitStatuses::read(root).unwrap();
+        let status = |path: &str| statuses.get(&root.join(path));
+        assert_eq!(status(".gitignore"), None);
+        assert_eq!(status("src/lib.rs"), None);
+        assert_eq!(status("src/deep/main.rs"), Some(GitStatus::Modified));
+        assert_eq!(status("src/staged.rs"), Some(GitStatus::Staged));
+        assert_eq!(status("src/deep"), Some(GitStatus::Modified));
+        assert_eq!(status("src"), Some(GitStatus::Modified));
+        assert_eq!(status("new"), Some(GitStatus::Untracked));
+        assert_eq!(status("new/file"), Some(GitStatus::Untracked));
+        assert_eq!(status("target/out"), Some(GitStatus::Ignored));
+        assert_eq!(status(""), Some(GitStatus::Modified));
+
+        assert!(statuses.is_affected_by(&root.join("src").join("deep")));
+        assert!(statuses.is_affected_by(&root.join(".git")));
+        assert!(!statuses.is_affected_by(&root.join("target")));
+        assert!(!statuses.is_affected_by(root.parent().unwrap()));
+
+        let statuses = GitStatuses::read(&root.join("src")).unwrap();
+        assert_eq!(statuses.get(&root.join("new")), None);
+        assert_eq!(
+            statuses.get(&root.join("src").join("staged.rs")),
+            Some(GitStatus::Staged)
+        );
+    }
+}
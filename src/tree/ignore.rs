@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use strum::{Display, EnumString};
+use tracing::warn;
+
+/// How entries are shown which are ignored by git or an `.ignore` file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum IgnoredEntries {
+    Hide,
+    /// show them with the `ignored` style
+    #[default]
+    Dim,
+    Show,
+}
+
+/// The ignore files which apply to the entries of a single directory.
+#[derive(Debug, Clone, Default)]
+struct DirRules {
+    /// whether the directory is part of a git repository
+    in_repo: bool,
+    /// the ignore files of the directory and its ancestors, from the lowest to the highest
+    /// precedence
+    matchers: Vec<Arc<Gitignore>>,
+}
+
+/// Decides which entries are ignored by `.gitignore`, `.ignore`, `.git/info/exclude` and the
+/// global excludes file of git.
+///
+/// The ignore files are read once per directory when its first entry is checked. Git ignore
+/// files only apply inside of a repository, `.ignore` files apply everywhere.
+///
+/// Clones share the read ignore files, so the entries of a directory can be checked on the
+/// thread which reads the directory.
+#[derive(Clone)]
+pub struct IgnoreRules {
+    global: Arc<Gitignore>,
+    dirs: Arc<Mutex<HashMap<PathBuf, Arc<DirRules>>>>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> Self {
+        let (global, err) = Gitignore::global();
+        if let Some(err) = err {
+            warn!("Failed to read the global git excludes: {}", err);
+        }
+        Self {
+            global: Arc::new(global),
+            dirs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether the entry at `path` is ignored, not taking its ancestors into account.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(dir) = path.parent() else {
+            return false;
+        };
+        let rules = self.rules(dir);
+
+        // the last match of the ignore file with the highest precedence wins
+        for matcher in rules.matchers.iter().rev() {
+            let matched = matcher.matched(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        rules.in_repo && self.global.matched(path, is_dir).is_ignore()
+    }
+
+    /// Forget the ignore files of `dir` and everything below it, so they are read again.
+    pub fn invalidate(&self, dir: &Path) {
+        self.cached().retain(|cached, _| !cached.starts_with(dir));
+    }
+
+    fn cached(&self) -> MutexGuard<'_, HashMap<PathBuf, Arc<DirRules>>> {
+        // every change of the cache is a single call, so it cannot be left half updated
+        self.dirs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn rules(&self, dir: &Path) -> Arc<DirRules> {
+        if let Some(rules) = self.cached().get(dir) {
+            return rules.clone();
+        }
+        // the files are read without holding the lock, so other directories can be checked
+        // in the meantime
+
+        let is_repo_root = dir.join(".git").exists();
+        let mut rules = if is_repo_root {
+            // ignore files outside of the repository do not apply to it
+            DirRules {
+                in_repo: true,
+                matchers: Vec::new(),
+            }
+        } else {
+            match dir.parent() {
+                Some(parent) => DirRules::clone(&self.rules(parent)),
+                None => DirRules::default(),
+            }
+        };
+
+        let mut files = Vec::new();
+        if is_repo_root {
+            files.push(dir.join(".git").join("info").join("exclude"));
+        }
+        if rules.in_repo {
+            files.push(dir.join(".gitignore"));
+        }
+        files.push(dir.join(".ignore"));
+
+        let mut builder = GitignoreBuilder::new(dir);
+        for file in files.iter().filter(|file| file.is_file()) {
+            if let Some(err) = builder.add(file) {
+                warn!("Failed to read {:?}: {}", file, err);
+            }
+        }
+        match builder.build() {
+            Ok(matcher) if !matcher.is_empty() => rules.matchers.push(Arc::new(matcher)),
+            Ok(_) => {}
+            Err(err) => warn!("Failed to read the ignore files of {:?}: {}", dir, err),
+        }
+
+        let rules = Arc::new(rules);
+        self.cached().insert(dir.to_path_buf(), rules.clone());
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_ignore_files_of_ancestors_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git").join("info")).unwrap();
+        fs::write(root.join(".git").join("info").join("exclude"), "*.tmp\n").unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src").join(".gitignore"), "!keep.log\n").unwrap();
+        fs::write(root.join("src").join(".ignore"), "generated.rs\n").unwrap();
+
+        let rules = IgnoreRules::new();
+        assert!(rules.is_ignored(&root.join("target"), true));
+        assert!(!rules.is_ignored(&root.join("target"), false));
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+        assert!(rules.is_ignored(&root.join("scratch.tmp"), false));
+        assert!(rules.is_ignored(&root.join("src").join("debug.log"), false));
+        assert!(!rules.is_ignored(&root.join("src").join("keep.log"), false));
+        assert!(rules.is_ignored(&root.join("src").join("generated.rs"), false));
+        assert!(!rules.is_ignored(&root.join("src").join("main.rs"), false));
+
+        fs::write(root.join(".gitignore"), "").unwrap();
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+        rules.invalidate(root);
+        assert!(!rules.is_ignored(&root.join("debug.log"), false));
+    }
+
+    #[test]
+    fn test_gitignore_only_applies_inside_of_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.path().join(".ignore"), "*.tmp\n").unwrap();
+
+        let rules = IgnoreRules::new();
+        assert!(!rules.is_ignored(&dir.path().join("debug.log"), false));
+        assert!(rules.is_ignored(&dir.path().join("scratch.tmp"), false));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    fs::read_dir,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::tree::{ignore::IgnoreRules, node::Node};
+
+/// The maximum number of entries that are sent at once.
+const BATCH_SIZE: usize = 1000;
+/// Entries are sent at least this often, so slow file systems still show progress.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The progress of reading a directory in the background.
+#[derive(Debug)]
+pub enum LoadEvent {
+    /// new entries of the directory, which are not yet linked into the tree
+    Entries(Vec<Node>),
+    /// the directory was read completely or could not be read at all
+    Finished(io::Result<()>),
+}
+
+/// Read the entries of the directory at `path` on the blocking thread pool and hand them to
+/// `emit` in batches.
+///
+/// The ignore files of the directory are read again along with it, so the entries arrive
+/// with their ignored flag set. Reading stops as soon as `token` is cancelled, in which case
+/// no [`LoadEvent::Finished`] is emitted.
+pub fn load_in_background(
+    path: PathBuf,
+    ignores: IgnoreRules,
+    token: CancellationToken,
+    mut emit: impl FnMut(LoadEvent) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        ignores.invalidate(&path);
+        let entries = match read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                emit(LoadEvent::Finished(Err(err)));
+                return;
+            }
+        };
+
+        let mut batch = Vec::new();
+        let mut last_sent = Instant::now();
+        for entry in entries.filter_map(Result::ok) {
+            if token.is_cancelled() {
+                return;
+            }
+            batch.push(read_node(&entry.path(), &ignores));
+
+            if batch.len() >= BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL {
+                emit(LoadEvent::Entries(std::mem::take(&mut batch)));
+                last_sent = Instant::now();
+            }
+        }
+
+        if token.is_cancelled() {
+            return;
+        }
+        if !batch.is_empty() {
+            emit(LoadEvent::Entries(batch));
+        }
+        emit(LoadEvent::Finished(Ok(())));
+    })
+}
+
+/// Read all entries of the directory at `path` on the blocking thread pool and hand them
+/// to `emit` at once.
+pub fn read_in_background(
+    path: PathBuf,
+    ignores: IgnoreRules,
+    emit: impl FnOnce(io::Result<Vec<Node>>) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || emit(read_entries(&path, &ignores)))
+}
+
+/// Read all entries of the directory at `path` together with its ignore files.
+pub fn read_entries(path: &Path, ignores: &IgnoreRules) -> io::Result<Vec<Node>> {
+    ignores.invalidate(path);
+    let nodes = read_dir(path)?
+        .filter_map(Result::ok)
+        .map(|entry| read_node(&entry.path(), ignores))
+        .collect();
+    Ok(nodes)
+}
+
+/// Create the node of the entry at `path`, which is not linked into the tree yet.
+fn read_node(path: &Path, ignores: &IgnoreRules) -> Node {
+    let mut node = Node::new(path, 0, None);
+    node.ignored = ignores.is_ignored(path, node.is_dir());
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..(BATCH_SIZE + 10) {
+            fs::write(dir.path().join(format!("{i}.txt")), "").unwrap();
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        load_in_background(
+            dir.path().to_path_buf(),
+            IgnoreRules::new(),
+            CancellationToken::new(),
+            move |event| sink.lock().unwrap().push(event),
+        )
+        .await
+        .unwrap();
+
+        let events = events.lock().unwrap();
+        let entries: usize = events
+            .iter()
+            .map(|event| match event {
+                LoadEvent::Entries(nodes) => nodes.len(),
+                LoadEvent::Finished(_) => 0,
+            })
+            .sum();
+        assert_eq!(entries, BATCH_SIZE + 10);
+        assert!(matches!(events.last(), Some(LoadEvent::Finished(Ok(())))));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_load_emits_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file.txt"), "").unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        load_in_background(
+            dir.path().to_path_buf(),
+            IgnoreRules::new(),
+            token,
+            move |event| sink.lock().unwrap().push(event),
+        )
+        .await
+        .unwrap();
+
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_entries_with_their_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kept.txt"), "").unwrap();
+        fs::write(dir.path().join("scratch.tmp"), "").unwrap();
+        let ignores = IgnoreRules::new();
+        let ignored = |ignores: &IgnoreRules| -> Vec<String> {
+            let mut nodes = read_entries(dir.path(), ignores).unwrap();
+            nodes.sort_by(|a, b| a.path.cmp(&b.path));
+            nodes
+                .iter()
+                .filter(|node| node.ignored)
+                .map(|node| node.name().into_owned())
+                .collect()
+        };
+        assert!(ignored(&ignores).is_empty());
+
+        // the ignore files are read again with the directory
+        fs::write(dir.path().join(".ignore"), "*.tmp\n").unwrap();
+        assert_eq!(ignored(&ignores), vec!["scratch.tmp"]);
+    }
+}
@@ -0,0 +1,806 @@
+pub mod archive;
+pub mod details;
+pub mod filter;
+pub mod git;
+pub mod ignore;
+pub mod loader;
+pub mod node;
+pub mod search;
+pub mod sort;
+pub mod usage;
+pub mod watch;
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::{Metadata, read_dir},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tracing::debug;
+
+use crate::{
+    arena::{Arena, Handle},
+    tree::{
+        archive::ArchivedEntry,
+        filter::Filter,
+        ignore::IgnoreRules,
+        node::{LoadState, Node, NodeKind},
+        sort::{SortKey, SortOrder},
+        usage::DiskUsage,
+    },
+};
+
+/// The file system tree that is browsed by lsn.
+///
+/// Every entry lives in a slot of an [`Arena`] and is addressed by a [`Handle`].
+/// When a directory is reloaded its old children are removed from the arena, so handles
+/// which still point to them are detected as stale by the generation check.
+pub struct FileTree {
+    arena: Arena<Node>,
+    root: Handle,
+    ignores: IgnoreRules,
+    /// the order of the children of every directory without an order of its own
+    sort: SortOrder,
+    /// the orders of single directories, by their path so they survive reloads
+    dir_sorts: HashMap<PathBuf, SortOrder>,
+}
+
+impl FileTree {
+    /// Create a new tree for `root` and load its direct children.
+    pub fn new(root: &Path) -> io::Result<Self> {
+        let mut arena = Arena::new();
+        let root = arena.insert(Node::new(root, 0, None));
+        let mut tree = Self {
+            arena,
+            root,
+            ignores: IgnoreRules::new(),
+            sort: SortOrder::default(),
+            dir_sorts: HashMap::new(),
+        };
+        tree.load_children(root)?;
+        tree.set_open(root, true);
+        Ok(tree)
+    }
+
+    pub fn root(&self) -> Handle {
+        self.root
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&Node> {
+        self.arena.get(&handle)
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut Node> {
+        self.arena.get_mut(&handle)
+    }
+
+    /// Find the node of `path` if it and all of its ancestors below the root are loaded.
+    pub fn find(&self, path: &Path) -> Option<Handle> {
+        let relative = path.strip_prefix(&self.get(self.root)?.path).ok()?;
+
+        let mut handle = self.root;
+        for component in relative.components() {
+            let child_path = self.get(handle)?.path.join(component);
+            handle = self
+                .children(handle)
+                .find(|child| self.get(*child).is_some_and(|node| node.path == child_path))?;
+        }
+        Some(handle)
+    }
+
+    /// Iterate over the direct children of `handle` in their sorted order.
+    pub fn children(&self, handle: Handle) -> Children<'_> {
+        Children {
+            tree: self,
+            next: self.get(handle).and_then(|node| node.first_child),
+        }
+    }
+
+    /// Read the directory or archive behind `handle` and replace its children.
+    pub fn load_children(&mut self, handle: Handle) -> io::Result<()> {
+        let Some(node) = self.get(handle) else {
+            return Ok(());
+        };
+        match node.kind {
+            NodeKind::Directory { .. } => {}
+            NodeKind::Archive { .. } => {
+                let entries = archive::list(&node.path)?;
+                self.clear_children(handle);
+                self.add_archived(handle, entries);
+                self.set_load_state(handle, LoadState::Loaded);
+                return Ok(());
+            }
+            NodeKind::File | NodeKind::Archived { .. } => return Ok(()),
+        }
+
+        let paths = read_dir(&node.path)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect();
+        self.set_children(handle, paths);
+
+        debug!(
+            "Loaded children of {:?}, the tree now holds {} nodes",
+            self.get(handle).map(|node| &node.path),
+            self.arena.len()
+        );
+        Ok(())
+    }
+
+    /// Replace all children of `handle` with new nodes for `paths`.
+    ///
+    /// The previous children and all of their descendants are removed from the arena.
+    pub fn set_children(&mut self, handle: Handle, paths: Vec<PathBuf>) {
+        if let Some(node) = self.get(handle) {
+            self.ignores.invalidate(&node.path);
+        }
+        let nodes = paths
+            .iter()
+            .map(|path| {
+                let mut node = Node::new(path, 0, None);
+                node.ignored = self.ignores.is_ignored(path, node.is_dir());
+                node
+            })
+            .collect();
+        self.clear_children(handle);
+        self.add_children(handle, nodes);
+        self.set_load_state(handle, LoadState::Loaded);
+    }
+
+    /// The ignore files that decide which entries of the directories are ignored.
+    pub fn ignore_rules(&self) -> &IgnoreRules {
+        &self.ignores
+    }
+
+    /// Remove all children of `handle` and their descendants from the arena.
+    pub fn clear_children(&mut self, handle: Handle) {
+        let old_children: Vec<Handle> = self.children(handle).collect();
+        for child in old_children {
+            self.remove_subtree(child);
+        }
+        if let Some(node) = self.get_mut(handle) {
+            node.first_child = None;
+        }
+    }
+
+    /// Add `nodes` to the children of `handle`, keeping all children in their sorted order.
+    ///
+    /// The depth and parent of the nodes are set to match their new position in the tree.
+    /// The nodes are ignored if they were read as ignored or `handle` is ignored.
+    pub fn add_children(&mut self, handle: Handle, mut nodes: Vec<Node>) {
+        let Some(node) = self.get(handle) else {
+            return;
+        };
+        let depth = node.depth + 1;
+        let parent_ignored = node.ignored;
+        for node in nodes.iter_mut() {
+            node.depth = depth;
+            node.parent = Some(handle);
+            node.ignored |= parent_ignored;
+        }
+        let order = self.sort_order(handle).clone();
+        order.sort(&mut nodes);
+
+        // merge the new nodes into the already sorted children
+        let mut children = Vec::with_capacity(nodes.len());
+        let mut existing = self
+            .children(handle)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        for node in nodes {
+            while let Some(&child) = existing.peek()
+                && self
+                    .get(child)
+                    .is_some_and(|child| order.compare(child, &node).is_lt())
+            {
+                children.push(child);
+                existing.next();
+            }
+            children.push(self.arena.insert(node));
+        }
+        children.extend(existing);
+        self.link_children(handle, &children);
+    }
+
+    /// Add the entries of the archive `handle` below it, nested like inside of the archive.
+    pub fn add_archived(&mut self, handle: Handle, entries: Vec<ArchivedEntry>) {
+        let mut by_parent: HashMap<PathBuf, Vec<Node>> = HashMap::new();
+        for entry in entries {
+            let parent = entry.inner.parent().unwrap_or(Path::new("")).to_path_buf();
+            by_parent
+                .entry(parent)
+                .or_default()
+                .push(Node::archived(entry));
+        }
+
+        let mut stack = vec![(handle, PathBuf::new())];
+        while let Some((parent, inner)) = stack.pop() {
+            let Some(nodes) = by_parent.remove(&inner) else {
+                continue;
+            };
+            self.add_children(parent, nodes);
+            for child in self.children(parent) {
+                if let Some(entry) = self.get(child).and_then(Node::archived_entry)
+                    && entry.is_dir
+                {
+                    stack.push((child, entry.inner.clone()));
+                }
+            }
+        }
+    }
+
+    /// Update the children of `handle` to match `nodes`, which is a fresh listing of the
+    /// directory.
+    ///
+    /// Children which still exist keep their handle, so their open state and their loaded
+    /// descendants are preserved. Vanished children are removed together with their
+    /// descendants and their paths are returned.
+    pub fn patch_children(&mut self, handle: Handle, nodes: Vec<Node>) -> Vec<PathBuf> {
+        let Some(parent) = self.get(handle) else {
+            return Vec::new();
+        };
+        let parent_ignored = parent.ignored;
+
+        let mut fresh: HashMap<PathBuf, Node> = nodes
+            .into_iter()
+            .map(|node| (node.path.clone(), node))
+            .collect();
+
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        let mut changed_archives = Vec::new();
+        for child in self.children(handle).collect::<Vec<_>>() {
+            let Some(node) = self.arena.get_mut(&child) else {
+                continue;
+            };
+            match fresh.remove(&node.path) {
+                // an entry which turned from a file into a directory or vice versa is replaced
+                Some(new) if new.is_dir() == node.is_dir() => {
+                    if matches!(node.kind, NodeKind::Archive { .. })
+                        && modified(&node.metadata) != modified(&new.metadata)
+                    {
+                        changed_archives.push(child);
+                    }
+                    node.metadata = new.metadata;
+                    node.symlink_target = new.symlink_target;
+                    // the ignore files of the directory may have changed as well
+                    node.ignored = parent_ignored || new.ignored;
+                    kept.push(child);
+                }
+                Some(new) => {
+                    fresh.insert(new.path.clone(), new);
+                    removed.push(child);
+                }
+                None => removed.push(child),
+            }
+        }
+
+        let removed_paths = removed
+            .into_iter()
+            .filter_map(|child| {
+                let path = self.get(child).map(|node| node.path.clone());
+                self.remove_subtree(child);
+                path
+            })
+            .collect();
+
+        // the listing of a changed archive is read again when it is expanded the next time
+        for archive in changed_archives {
+            self.clear_children(archive);
+            self.set_load_state(archive, LoadState::NotLoaded);
+            self.set_open(archive, false);
+        }
+
+        // the kept children are still in their sorted order
+        self.link_children(handle, &kept);
+        self.add_children(handle, fresh.into_values().collect());
+        removed_paths
+    }
+
+    /// The order of the children of all directories without an order of their own.
+    pub fn global_sort_order(&self) -> &SortOrder {
+        &self.sort
+    }
+
+    /// The order of the children of the directory `handle`.
+    pub fn sort_order(&self, handle: Handle) -> &SortOrder {
+        self.get(handle)
+            .and_then(|node| self.dir_sorts.get(&node.path))
+            .unwrap_or(&self.sort)
+    }
+
+    /// Whether the directory `handle` has an order of its own.
+    pub fn has_own_sort_order(&self, handle: Handle) -> bool {
+        self.get(handle)
+            .is_some_and(|node| self.dir_sorts.contains_key(&node.path))
+    }
+
+    /// Change the order of all directories without an order of their own.
+    pub fn set_sort_order(&mut self, order: SortOrder) {
+        if order == self.sort {
+            return;
+        }
+        self.sort = order;
+        // directories which are still loading are sorted as well, so their entries are
+        // merged in the right place
+        for dir in self.walk() {
+            if self.get(dir).is_some_and(Node::is_expandable) && !self.has_own_sort_order(dir) {
+                self.resort(dir);
+            }
+        }
+    }
+
+    /// Give the directory `handle` an order of its own, or let it use the order of all
+    /// directories again if `order` is `None`.
+    pub fn set_dir_sort_order(&mut self, handle: Handle, order: Option<SortOrder>) {
+        let Some(node) = self.get(handle) else {
+            return;
+        };
+        let path = node.path.clone();
+        match order {
+            Some(order) => self.dir_sorts.insert(path, order),
+            None => self.dir_sorts.remove(&path),
+        };
+        self.resort(handle);
+    }
+
+    /// Replace the orders of single directories, e.g. with the ones of a changed config.
+    pub fn set_dir_sort_orders(&mut self, orders: HashMap<PathBuf, SortOrder>) {
+        if orders == self.dir_sorts {
+            return;
+        }
+        self.dir_sorts = orders;
+        for dir in self.walk() {
+            if self.get(dir).is_some_and(Node::is_expandable) {
+                self.resort(dir);
+            }
+        }
+    }
+
+    /// Store the measured disk usage of the directory `handle`, which moves it to its new
+    /// place if its siblings are sorted by size.
+    pub fn set_usage(&mut self, handle: Handle, usage: DiskUsage) {
+        let Some(node) = self.get_mut(handle) else {
+            return;
+        };
+        node.usage = Some(usage);
+        if let Some(parent) = node.parent
+            && self
+                .sort_order(parent)
+                .keys
+                .iter()
+                .any(|sort_by| sort_by.key == SortKey::Size)
+        {
+            self.resort(parent);
+        }
+    }
+
+    /// Sort the children of `handle` again, e.g. after its order changed.
+    fn resort(&mut self, handle: Handle) {
+        let order = self.sort_order(handle);
+        let mut children: Vec<Handle> = self.children(handle).collect();
+        children.sort_by(|a, b| match (self.get(*a), self.get(*b)) {
+            (Some(a), Some(b)) => order.compare(a, b),
+            _ => Ordering::Equal,
+        });
+        self.link_children(handle, &children);
+    }
+
+    /// Link `children` as the children of `handle` in the given order.
+    fn link_children(&mut self, handle: Handle, children: &[Handle]) {
+        let mut next_sibling = None;
+        for child in children.iter().rev() {
+            if let Some(node) = self.get_mut(*child) {
+                node.next_sibling = next_sibling;
+            }
+            next_sibling = Some(*child);
+        }
+        if let Some(node) = self.get_mut(handle) {
+            node.first_child = next_sibling;
+        }
+    }
+
+    pub fn set_load_state(&mut self, handle: Handle, state: LoadState) {
+        if let Some(node) = self.get_mut(handle)
+            && let NodeKind::Directory { load, .. } | NodeKind::Archive { load, .. } =
+                &mut node.kind
+        {
+            *load = state;
+        }
+    }
+
+    pub fn set_open(&mut self, handle: Handle, open: bool) {
+        let Some(node) = self.get_mut(handle) else {
+            return;
+        };
+        if !node.is_expandable() {
+            return;
+        }
+        if let NodeKind::Directory { is_open, .. }
+        | NodeKind::Archive { is_open, .. }
+        | NodeKind::Archived { is_open, .. } = &mut node.kind
+        {
+            *is_open = open;
+        }
+    }
+
+    /// All loaded nodes in the order they are shown when every directory is open.
+    pub fn walk(&self) -> Vec<Handle> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(handle) = stack.pop() {
+            nodes.push(handle);
+            let children: Vec<Handle> = self.children(handle).collect();
+            stack.extend(children.into_iter().rev());
+        }
+
+        nodes
+    }
+
+    /// All directories whose children are loaded, whether they are open or not.
+    pub fn loaded_dirs(&self) -> Vec<Handle> {
+        let mut loaded = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(handle) = stack.pop() {
+            if let Some(NodeKind::Directory {
+                load: LoadState::Loaded,
+                ..
+            }) = self.get(handle).map(|node| &node.kind)
+            {
+                loaded.push(handle);
+                stack.extend(self.children(handle));
+            }
+        }
+
+        loaded
+    }
+
+    /// Flatten the tree into the list of handles which are currently visible,
+    /// i.e. the root and every node whose ancestors are all open.
+    pub fn flatten(&self) -> Vec<Handle> {
+        self.flatten_from(self.root)
+    }
+
+    /// Flatten the tree like [`FileTree::flatten`], but only `handle` and its descendants.
+    pub fn flatten_from(&self, handle: Handle) -> Vec<Handle> {
+        let mut visible = Vec::new();
+        let mut stack = vec![handle];
+
+        while let Some(handle) = stack.pop() {
+            let Some(node) = self.get(handle) else {
+                continue;
+            };
+            visible.push(handle);
+
+            if node.is_open() {
+                let children: Vec<Handle> = self.children(handle).collect();
+                stack.extend(children.into_iter().rev());
+            }
+        }
+
+        visible
+    }
+
+    /// Flatten the tree like [`FileTree::flatten`], but leave out the nodes which are hidden
+    /// by `filter`.
+    ///
+    /// Directories stay visible if anything below them is shown, so every shown node is
+    /// still seen in the context of its ancestors. Directories whose children are not
+    /// loaded yet are shown as well, since they may contain matches.
+    pub fn flatten_filtered(&self, filter: &Filter) -> Vec<Handle> {
+        if filter.is_empty() {
+            return self.flatten();
+        }
+        let mut visible = Vec::new();
+        if let Some(root) = self.get(self.root) {
+            visible.push(self.root);
+            for child in self.children(self.root) {
+                self.flatten_below(child, filter, &root.path, false, &mut visible);
+            }
+        }
+        visible
+    }
+
+    /// Add `handle` and its visible descendants to `visible` if it is not filtered out.
+    ///
+    /// `included` is set if an ancestor matches an include of the filter. Returns whether
+    /// `handle` is visible.
+    fn flatten_below(
+        &self,
+        handle: Handle,
+        filter: &Filter,
+        root: &Path,
+        included: bool,
+        visible: &mut Vec<Handle>,
+    ) -> bool {
+        let Some(node) = self.get(handle) else {
+            return false;
+        };
+        let relative = node.path.strip_prefix(root).unwrap_or(&node.path);
+        if filter.excludes(node, relative) {
+            return false;
+        }
+        let included = included || !filter.has_includes() || filter.includes(node, relative);
+
+        let Some(load) = node.load_state() else {
+            if included {
+                visible.push(handle);
+            }
+            return included;
+        };
+        let is_open = node.is_open();
+
+        let start = visible.len();
+        visible.push(handle);
+        let shown_below = if is_open {
+            let mut shown = false;
+            // every child has to be visited, so all visible ones are added
+            for child in self.children(handle) {
+                shown |= self.flatten_below(child, filter, root, included, visible);
+            }
+            shown
+        } else {
+            !included && self.matches_below(handle, filter, root)
+        };
+        // unlike a directory, an archive which is not loaded is only shown if it is included
+        if included || shown_below || (load != LoadState::Loaded && node.is_dir()) {
+            return true;
+        }
+        visible.truncate(start);
+        false
+    }
+
+    /// Whether any loaded node below `handle` would be visible with `filter`.
+    fn matches_below(&self, handle: Handle, filter: &Filter, root: &Path) -> bool {
+        let mut stack: Vec<Handle> = self.children(handle).collect();
+        while let Some(handle) = stack.pop() {
+            let Some(node) = self.get(handle) else {
+                continue;
+            };
+            let relative = node.path.strip_prefix(root).unwrap_or(&node.path);
+            if filter.excludes(node, relative) {
+                continue;
+            }
+            if filter.includes(node, relative) {
+                return true;
+            }
+            match node.load_state() {
+                Some(LoadState::Loaded) => stack.extend(self.children(handle)),
+                // the directory may contain matches which are not loaded yet
+                Some(_) if node.is_dir() => return true,
+                Some(_) | None => {}
+            }
+        }
+        false
+    }
+
+    /// Remove `handle` and all of its descendants from the arena.
+    ///
+    /// The caller is responsible for unlinking `handle` from its parent and siblings.
+    fn remove_subtree(&mut self, handle: Handle) {
+        let mut stack = vec![handle];
+        while let Some(handle) = stack.pop() {
+            stack.extend(self.children(handle));
+            self.arena.remove(handle);
+        }
+    }
+}
+
+fn modified(metadata: &Option<Metadata>) -> Option<SystemTime> {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.modified().ok())
+}
+
+pub struct Children<'a> {
+    tree: &'a FileTree,
+    next: Option<Handle>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let handle = self.next?;
+        self.next = self.tree.get(handle).and_then(|node| node.next_sibling);
+        Some(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::tree::filter::Pattern;
+
+    fn fixture() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("b_dir")).unwrap();
+        fs::write(dir.path().join("b_dir").join("nested.txt"), "").unwrap();
+        fs::create_dir(dir.path().join("c_dir")).unwrap();
+        fs::write(dir.path().join("a_file.txt"), "").unwrap();
+        dir
+    }
+
+    fn names(tree: &FileTree, handles: impl IntoIterator<Item = Handle>) -> Vec<String> {
+        handles
+            .into_iter()
+            .map(|handle| tree.get(handle).unwrap().name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_new_loads_sorted_children() {
+        let dir = fixture();
+        let tree = FileTree::new(dir.path()).unwrap();
+
+        assert_eq!(
+            names(&tree, tree.children(tree.root())),
+            vec!["b_dir", "c_dir", "a_file.txt"]
+        );
+    }
+
+    #[test]
+    fn test_children_link_back_to_parent() {
+        let dir = fixture();
+        let tree = FileTree::new(dir.path()).unwrap();
+
+        for child in tree.children(tree.root()) {
+            let node = tree.get(child).unwrap();
+            assert_eq!(node.parent, Some(tree.root()));
+            assert_eq!(node.depth, 1);
+        }
+    }
+
+    #[test]
+    fn test_flatten_only_descends_into_open_directories() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let b_dir = tree.children(tree.root()).next().unwrap();
+
+        assert_eq!(tree.flatten().len(), 4);
+
+        tree.load_children(b_dir).unwrap();
+        assert_eq!(tree.flatten().len(), 4);
+
+        tree.set_open(b_dir, true);
+        assert_eq!(
+            names(&tree, tree.flatten().into_iter().skip(1)),
+            vec!["b_dir", "nested.txt", "c_dir", "a_file.txt"]
+        );
+    }
+
+    #[test]
+    fn test_reload_invalidates_old_handles() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let b_dir = tree.children(tree.root()).next().unwrap();
+        tree.load_children(b_dir).unwrap();
+        let nested = tree.children(b_dir).next().unwrap();
+
+        fs::write(dir.path().join("d_file.txt"), "").unwrap();
+        tree.load_children(tree.root()).unwrap();
+
+        assert!(tree.get(b_dir).is_none());
+        assert!(tree.get(nested).is_none());
+        assert_eq!(tree.children(tree.root()).count(), 4);
+    }
+
+    #[test]
+    fn test_add_children_keeps_sort_order() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let root = tree.root();
+
+        fs::create_dir(dir.path().join("a_dir")).unwrap();
+        fs::write(dir.path().join("0_file.txt"), "").unwrap();
+        fs::write(dir.path().join("z_file.txt"), "").unwrap();
+        let nodes = ["z_file.txt", "a_dir", "0_file.txt"]
+            .iter()
+            .map(|name| Node::new(&dir.path().join(name), 0, None))
+            .collect();
+        tree.add_children(root, nodes);
+
+        assert_eq!(
+            names(&tree, tree.children(root)),
+            vec![
+                "a_dir",
+                "b_dir",
+                "c_dir",
+                "0_file.txt",
+                "a_file.txt",
+                "z_file.txt"
+            ]
+        );
+        let a_dir = tree.children(root).next().unwrap();
+        assert_eq!(tree.get(a_dir).unwrap().parent, Some(root));
+        assert_eq!(tree.get(a_dir).unwrap().depth, 1);
+    }
+
+    #[test]
+    fn test_patch_children_preserves_kept_subtrees() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let root = tree.root();
+        let b_dir = tree.children(root).next().unwrap();
+        tree.load_children(b_dir).unwrap();
+        tree.set_open(b_dir, true);
+        let nested = tree.children(b_dir).next().unwrap();
+        let a_file = tree.children(root).last().unwrap();
+
+        fs::remove_dir(dir.path().join("c_dir")).unwrap();
+        fs::write(dir.path().join("d_file.txt"), "").unwrap();
+        let listing = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| Node::new(&entry.unwrap().path(), 0, None))
+            .collect();
+        let removed = tree.patch_children(root, listing);
+
+        assert_eq!(removed, vec![dir.path().join("c_dir")]);
+        assert_eq!(
+            names(&tree, tree.children(root)),
+            vec!["b_dir", "a_file.txt", "d_file.txt"]
+        );
+        assert_eq!(tree.children(root).next(), Some(b_dir));
+        assert_eq!(tree.children(b_dir).next(), Some(nested));
+        assert!(tree.get(a_file).is_some());
+        assert_eq!(tree.flatten().len(), 5);
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let b_dir = tree.children(tree.root()).next().unwrap();
+
+        assert_eq!(tree.find(dir.path()), Some(tree.root()));
+        assert_eq!(tree.find(&dir.path().join("b_dir/nested.txt")), None);
+
+        tree.load_children(b_dir).unwrap();
+        let nested = tree.children(b_dir).next();
+        assert_eq!(tree.find(&dir.path().join("b_dir/nested.txt")), nested);
+        assert_eq!(tree.find(Path::new("/somewhere/else")), None);
+    }
+
+    #[test]
+    fn test_flatten_filtered_keeps_ancestors_of_matches() {
+        let dir = fixture();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+        let mut children = tree.children(tree.root());
+        let (b_dir, c_dir) = (children.next().unwrap(), children.next().unwrap());
+        let filter = |line: &str| Filter::new(Pattern::parse_all(line).unwrap());
+        let visible = |tree: &FileTree, line: &str| {
+            names(
+                tree,
+                tree.flatten_filtered(&filter(line)).into_iter().skip(1),
+            )
+        };
+
+        // directories which are not loaded yet may contain matches
+        assert_eq!(
+            visible(&tree, "*.txt"),
+            vec!["b_dir", "c_dir", "a_file.txt"]
+        );
+
+        tree.load_children(b_dir).unwrap();
+        tree.load_children(c_dir).unwrap();
+        assert_eq!(visible(&tree, "*.txt"), vec!["b_dir", "a_file.txt"]);
+
+        tree.set_open(b_dir, true);
+        assert_eq!(visible(&tree, "nested.*"), vec!["b_dir", "nested.txt"]);
+        assert_eq!(visible(&tree, "b_dir"), vec!["b_dir", "nested.txt"]);
+        assert_eq!(visible(&tree, "*.txt !b_dir"), vec!["a_file.txt"]);
+        assert_eq!(visible(&tree, "/^[ab]_/ !*.txt"), vec!["b_dir"]);
+        assert_eq!(tree.flatten_filtered(&Filter::default()), tree.flatten());
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    borrow::Cow,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::{
+    arena::Handle,
+    tree::{
+        archive::{ArchiveFormat, ArchivedEntry},
+        usage::DiskUsage,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    Directory {
+        is_open: bool,
+        load: LoadState,
+    },
+    File,
+    /// an archive file which can be expanded like a directory to browse its entries
+    Archive {
+        is_open: bool,
+        load: LoadState,
+    },
+    /// an entry inside of an archive, which only exists in the listing of the archive. The
+    /// entries below an archived directory are always loaded together with the archive.
+    Archived {
+        entry: ArchivedEntry,
+        is_open: bool,
+    },
+}
+
+/// Whether the children of a directory have been read from disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadState {
+    NotLoaded,
+    /// the children are read in the background and added as they arrive
+    Loading,
+    Loaded,
+}
+
+/// A single entry of the file tree, stored in a slot of the tree's arena.
+///
+/// Nodes do not own their children. Instead they are linked through handles:
+/// a directory points to its first child and every child points to its next sibling
+/// and back to its parent.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub path: PathBuf,
+    pub kind: NodeKind,
+    pub depth: usize,
+    /// the metadata of the entry itself, i.e. of the link and not its target for symlinks
+    pub metadata: Option<Metadata>,
+    pub symlink_target: Option<PathBuf>,
+    /// whether the entry or one of its ancestors is ignored, e.g. by a `.gitignore` file
+    pub ignored: bool,
+    /// the disk usage of a directory and everything below it, once it was measured
+    pub usage: Option<DiskUsage>,
+    pub parent: Option<Handle>,
+    pub first_child: Option<Handle>,
+    pub next_sibling: Option<Handle>,
+}
+
+impl Node {
+    pub fn new(path: &Path, depth: usize, parent: Option<Handle>) -> Self {
+        let metadata = fs::symlink_metadata(path).ok();
+        let is_symlink = metadata
+            .as_ref()
+            .is_some_and(|m| m.file_type().is_symlink());
+        let symlink_target = if is_symlink {
+            fs::read_link(path).ok()
+        } else {
+            None
+        };
+        let is_dir = if is_symlink {
+            path.is_dir()
+        } else {
+            metadata.as_ref().is_some_and(|m| m.is_dir())
+        };
+
+        let kind = if is_dir {
+            NodeKind::Directory {
+                is_open: false,
+                load: LoadState::NotLoaded,
+            }
+        } else if ArchiveFormat::of(path).is_some() && path.is_file() {
+            NodeKind::Archive {
+                is_open: false,
+                load: LoadState::NotLoaded,
+            }
+        } else {
+            NodeKind::File
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            depth,
+            metadata,
+            symlink_target,
+            ignored: false,
+            usage: None,
+            parent,
+            first_child: None,
+            next_sibling: None,
+        }
+    }
+
+    /// The file name of the node, or the whole path if there is none, e.g. for `/`.
+    pub fn name(&self) -> Cow<'_, str> {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => self.path.to_string_lossy(),
+        }
+    }
+
+    /// A node for an entry of an archive, which has no metadata of its own.
+    pub fn archived(entry: ArchivedEntry) -> Self {
+        Self {
+            path: entry.archive.join(&entry.inner),
+            kind: NodeKind::Archived {
+                entry,
+                is_open: false,
+            },
+            depth: 0,
+            metadata: None,
+            symlink_target: None,
+            ignored: false,
+            usage: None,
+            parent: None,
+            first_child: None,
+            next_sibling: None,
+        }
+    }
+
+    /// Whether the node is a directory on disk or inside of an archive.
+    pub fn is_dir(&self) -> bool {
+        match &self.kind {
+            NodeKind::Directory { .. } => true,
+            NodeKind::Archived { entry, .. } => entry.is_dir,
+            NodeKind::File | NodeKind::Archive { .. } => false,
+        }
+    }
+
+    /// Whether the node can have children, i.e. it is a directory or an archive.
+    pub fn is_expandable(&self) -> bool {
+        self.load_state().is_some()
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self.kind {
+            NodeKind::Directory { is_open, .. }
+            | NodeKind::Archive { is_open, .. }
+            | NodeKind::Archived { is_open, .. } => is_open,
+            NodeKind::File => false,
+        }
+    }
+
+    /// Whether the children of an expandable node are read, or `None` if it can not have any.
+    pub fn load_state(&self) -> Option<LoadState> {
+        match &self.kind {
+            NodeKind::Directory { load, .. } | NodeKind::Archive { load, .. } => Some(*load),
+            NodeKind::Archived { entry, .. } if entry.is_dir => Some(LoadState::Loaded),
+            NodeKind::Archived { .. } | NodeKind::File => None,
+        }
+    }
+
+    /// The entry of the archive if the node is inside of one.
+    pub fn archived_entry(&self) -> Option<&ArchivedEntry> {
+        match &self.kind {
+            NodeKind::Archived { entry, .. } => Some(entry),
+            _ => None,
+        }
+    }
+}
+
+/// The serialized form of a node which is used by the JSON output.
+///
+/// The fields are part of the output format of lsn and must stay stable:
+/// `path`, `kind` (`directory` or `file`, symlinks are resolved), `depth`, `size` in bytes,
+/// `mtime` as RFC 3339 timestamp in UTC, `permissions` as octal unix mode and
+/// `symlink_target`. Unavailable values are `null`.
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let kind = if self.is_dir() { "directory" } else { "file" };
+        // entries of archives have no metadata, but their headers describe them as well
+        let (size, mtime, permissions) = match self.archived_entry() {
+            Some(entry) => (
+                Some(entry.size),
+                entry.mtime,
+                entry.mode.map(|mode| format!("{:04o}", mode & 0o7777)),
+            ),
+            None => (
+                self.metadata.as_ref().map(Metadata::len),
+                self.metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.modified().ok()),
+                self.metadata.as_ref().and_then(unix_mode),
+            ),
+        };
+        let mtime = mtime.and_then(|mtime| OffsetDateTime::from(mtime).format(&Rfc3339).ok());
+
+        let mut state = serializer.serialize_struct("Node", 7)?;
+        state.serialize_field("path", &self.path)?;
+        state.serialize_field("kind", kind)?;
+        state.serialize_field("depth", &self.depth)?;
+        state.serialize_field("size", &size)?;
+        state.serialize_field("mtime", &mtime)?;
+        state.serialize_field("permissions", &permissions)?;
+        state.serialize_field("symlink_target", &self.symlink_target)?;
+        state.end()
+    }
+}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(format!("{:04o}", metadata.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_metadata: &Metadata) -> Option<String> {
+    None
+}
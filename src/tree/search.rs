@@ -0,0 +1,74 @@
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+
+use crate::{arena::Handle, tree::FileTree};
+
+/// A node whose name matches a search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub handle: Handle,
+    pub score: i64,
+    /// the indices of the matched characters in the name of the node
+    pub indices: Vec<usize>,
+}
+
+/// Fuzzy match the names of all loaded nodes below the root against `query`.
+///
+/// The matches are returned in the order of [`FileTree::walk`], the root itself is never
+/// matched.
+pub fn find_matches(tree: &FileTree, query: &str) -> Vec<Match> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let matcher = SkimMatcherV2::default().smart_case();
+
+    tree.walk()
+        .into_iter()
+        .skip(1)
+        .filter_map(|handle| {
+            let node = tree.get(handle)?;
+            let (score, indices) = matcher.fuzzy_indices(&node.name(), query)?;
+            Some(Match {
+                handle,
+                score,
+                indices,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_find_matches_in_loaded_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src").join("main.rs"), "").unwrap();
+        fs::write(dir.path().join("Makefile"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        let mut tree = FileTree::new(dir.path()).unwrap();
+
+        let names = |tree: &FileTree, matches: Vec<Match>| -> Vec<String> {
+            matches
+                .iter()
+                .map(|m| tree.get(m.handle).unwrap().name().into_owned())
+                .collect()
+        };
+        assert_eq!(names(&tree, find_matches(&tree, "ma")), vec!["Makefile"]);
+
+        // directories which are loaded are searched even if they are closed
+        let src = tree.children(tree.root()).next().unwrap();
+        tree.load_children(src).unwrap();
+        let matches = find_matches(&tree, "ma");
+        assert_eq!(matches[0].indices, vec![0, 1]);
+        assert_eq!(names(&tree, matches), vec!["main.rs", "Makefile"]);
+
+        assert_eq!(find_matches(&tree, ""), vec![]);
+        assert_eq!(find_matches(&tree, "xyz"), vec![]);
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::Peekable,
+    str::{Chars, FromStr},
+    time::SystemTime,
+};
+
+use strum::{Display, EnumString};
+
+use crate::tree::node::Node;
+
+/// A property of the entries of a directory which they can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum SortKey {
+    /// the file name, with numbers compared by their value, e.g. `v2` before `v10`
+    Name,
+    /// the size of files and the measured disk usage of directories
+    Size,
+    /// the time of the last modification
+    Mtime,
+    /// the file extension, entries without one come first
+    Extension,
+    /// directories, then symlinks, then files
+    Kind,
+}
+
+/// A single key of a [`SortOrder`] and its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortBy {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+/// Written as the name of the key, with a leading `-` if it is descending, e.g. `-mtime`.
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, key) = match s.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, s),
+        };
+        let key = key.parse().map_err(|_| {
+            format!("unknown sort key `{key}`, expected name, size, mtime, extension or kind")
+        })?;
+        Ok(Self { key, descending })
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "-{}", self.key)
+        } else {
+            write!(f, "{}", self.key)
+        }
+    }
+}
+
+/// The order of the entries of a directory.
+///
+/// The entries are compared by the first key, ties are broken by the following keys and
+/// finally by their path, so the order is always total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortOrder {
+    pub keys: Vec<SortBy>,
+    /// sort directories before all other entries, regardless of the keys
+    pub dirs_first: bool,
+    /// compare names and extensions without regard to case
+    pub ignore_case: bool,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self {
+            keys: vec![SortBy {
+                key: SortKey::Name,
+                descending: false,
+            }],
+            dirs_first: true,
+            ignore_case: false,
+        }
+    }
+}
+
+impl SortOrder {
+    pub fn compare(&self, a: &Node, b: &Node) -> Ordering {
+        let dirs_first = if self.dirs_first {
+            b.is_dir().cmp(&a.is_dir())
+        } else {
+            Ordering::Equal
+        };
+        self.keys
+            .iter()
+            .fold(dirs_first, |ordering, sort_by| {
+                ordering.then_with(|| {
+                    let ordering = self.compare_by(sort_by.key, a, b);
+                    if sort_by.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+            })
+            .then_with(|| a.path.cmp(&b.path))
+    }
+
+    pub fn sort(&self, nodes: &mut [Node]) {
+        nodes.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// Make `key` the first key, keeping the others to break ties. If it already is the
+    /// first key, its direction is reversed instead.
+    pub fn prefer(&mut self, key: SortKey) {
+        match self.keys.first_mut() {
+            Some(first) if first.key == key => first.descending = !first.descending,
+            _ => {
+                self.keys.retain(|sort_by| sort_by.key != key);
+                self.keys.insert(
+                    0,
+                    SortBy {
+                        key,
+                        descending: false,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Reverse the direction of the first key.
+    pub fn reverse(&mut self) {
+        if let Some(first) = self.keys.first_mut() {
+            first.descending = !first.descending;
+        }
+    }
+
+    fn compare_by(&self, key: SortKey, a: &Node, b: &Node) -> Ordering {
+        match key {
+            SortKey::Name => self.compare_text(&a.name(), &b.name()),
+            SortKey::Size => size(a).cmp(&size(b)),
+            SortKey::Mtime => mtime(a).cmp(&mtime(b)),
+            SortKey::Extension => {
+                let extension = |node: &Node| {
+                    node.path
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().into_owned())
+                };
+                match (extension(a), extension(b)) {
+                    (Some(a), Some(b)) => self.compare_text(&a, &b),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                }
+            }
+            SortKey::Kind => kind(a).cmp(&kind(b)),
+        }
+    }
+
+    fn compare_text(&self, a: &str, b: &str) -> Ordering {
+        if self.ignore_case {
+            natural_cmp(&a.to_lowercase(), &b.to_lowercase())
+        } else {
+            natural_cmp(a, b)
+        }
+    }
+}
+
+/// Written like in the config, e.g. `-mtime, name, dirs first`.
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.keys.iter().map(ToString::to_string).collect();
+        if self.dirs_first {
+            parts.push("dirs first".to_string());
+        }
+        if self.ignore_case {
+            parts.push("ignore case".to_string());
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// The size of a file or the measured disk usage of a directory.
+fn size(node: &Node) -> u64 {
+    if let Some(entry) = node.archived_entry() {
+        entry.size
+    } else if node.is_dir() {
+        node.usage.map_or(0, |usage| usage.bytes)
+    } else {
+        node.metadata.as_ref().map_or(0, |metadata| metadata.len())
+    }
+}
+
+fn mtime(node: &Node) -> Option<SystemTime> {
+    match node.archived_entry() {
+        Some(entry) => entry.mtime,
+        None => node
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.modified().ok()),
+    }
+}
+
+fn kind(node: &Node) -> u8 {
+    if node.is_dir() {
+        0
+    } else if node.symlink_target.is_some() {
+        1
+    } else {
+        2
+    }
+}
+
+/// Compare two strings like a human would, i.e. runs of digits by their numeric value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_digits(&mut a), take_digits(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                // longer numbers are larger, which also works for numbers that overflow
+                x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                a.next();
+                b.next();
+                ordering
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["v10", "v9", "v1.10.0", "v1.9.2", "v01", "a", "V2"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["V2", "a", "v01", "v1.9.2", "v1.10.0", "v9", "v10"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_multiple_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("dir")).unwrap();
+        fs::write(dir.path().join("b.txt"), "12").unwrap();
+        fs::write(dir.path().join("A.rs"), "1").unwrap();
+        fs::write(dir.path().join("c.rs"), "12").unwrap();
+        let mut nodes: Vec<Node> = ["dir", "b.txt", "A.rs", "c.rs"]
+            .iter()
+            .map(|name| Node::new(&dir.path().join(name), 1, None))
+            .collect();
+        let names = |nodes: &[Node]| -> Vec<String> {
+            nodes.iter().map(|node| node.name().into_owned()).collect()
+        };
+
+        let mut order = SortOrder::default();
+        order.sort(&mut nodes);
+        assert_eq!(names(&nodes), vec!["dir", "A.rs", "b.txt", "c.rs"]);
+
+        order.keys = vec!["-size".parse().unwrap(), "extension".parse().unwrap()];
+        order.sort(&mut nodes);
+        assert_eq!(names(&nodes), vec!["dir", "c.rs", "b.txt", "A.rs"]);
+
+        order.keys = vec!["name".parse().unwrap()];
+        order.ignore_case = true;
+        order.prefer(SortKey::Name);
+        order.sort(&mut nodes);
+        assert_eq!(names(&nodes), vec!["dir", "c.rs", "b.txt", "A.rs"]);
+        assert_eq!(order.to_string(), "-name, dirs first, ignore case");
+
+        order.prefer(SortKey::Kind);
+        order.sort(&mut nodes);
+        assert_eq!(names(&nodes), vec!["dir", "c.rs", "b.txt", "A.rs"]);
+        assert_eq!(order.to_string(), "kind, -name, dirs first, ignore case");
+        assert!("-color".parse::<SortBy>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashSet,
+    fs::{Metadata, read_dir},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// The running total is sent at most this often.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The space taken by a directory and everything below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// the allocated size in bytes, counting hard linked files once
+    pub bytes: u64,
+    /// the number of entries below the directory which are not directories themselves
+    pub files: u64,
+}
+
+/// The progress of measuring a directory in the background.
+#[derive(Debug)]
+pub enum UsageEvent {
+    /// the usage of the entries which were counted so far
+    Progress(DiskUsage),
+    Finished(DiskUsage),
+}
+
+/// Measure the disk usage of the directory at `path` on the blocking thread pool, like `du`
+/// does, and report the progress to `emit`.
+///
+/// Symlinks are not followed and directories which cannot be read are skipped. Measuring
+/// stops as soon as `token` is cancelled, in which case nothing is emitted anymore.
+pub fn measure_in_background(
+    path: PathBuf,
+    token: CancellationToken,
+    mut emit: impl FnMut(UsageEvent) + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut usage = DiskUsage::default();
+        let mut linked = HashSet::new();
+        let mut last_sent = Instant::now();
+        let mut stack = vec![path];
+
+        while let Some(dir) = stack.pop() {
+            let Ok(entries) = read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                if token.is_cancelled() {
+                    return;
+                }
+                // the metadata of a symlink itself, so links are never followed
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    usage.bytes += allocated_size(&metadata);
+                    stack.push(entry.path());
+                } else if counted_first(&metadata, &mut linked) {
+                    usage.bytes += allocated_size(&metadata);
+                    usage.files += 1;
+                }
+            }
+
+            if last_sent.elapsed() >= PROGRESS_INTERVAL {
+                emit(UsageEvent::Progress(usage));
+                last_sent = Instant::now();
+            }
+        }
+
+        if !token.is_cancelled() {
+            emit(UsageEvent::Finished(usage));
+        }
+    })
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Whether the file is seen for the first time, which is only in doubt for hard links.
+#[cfg(unix)]
+fn counted_first(metadata: &Metadata, linked: &mut HashSet<(u64, u64)>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() <= 1 || linked.insert((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn counted_first(_metadata: &Metadata, _linked: &mut HashSet<(u64, u64)>) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_measure_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        fs::write(
+            dir.path().join("a").join("b").join("data"),
+            vec![1; 64 * 1024],
+        )
+        .unwrap();
+        fs::write(dir.path().join("a").join("small"), "x").unwrap();
+        fs::hard_link(
+            dir.path().join("a").join("small"),
+            dir.path().join("a").join("link"),
+        )
+        .unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        measure_in_background(
+            dir.path().to_path_buf(),
+            CancellationToken::new(),
+            move |event| sink.lock().unwrap().push(event),
+        )
+        .await
+        .unwrap();
+
+        let events = events.lock().unwrap();
+        let Some(UsageEvent::Finished(usage)) = events.last() else {
+            panic!("measuring did not finish: {events:?}");
+        };
+        assert_eq!(usage.files, 2);
+        assert!(usage.bytes >= 64 * 1024);
+    }
+}
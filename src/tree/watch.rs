@@ -0,0 +1,125 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::Result;
+use notify_debouncer_mini::{
+    Config, DebounceEventResult, Debouncer, new_debouncer_opt,
+    notify::{
+        self, Event, EventHandler, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+        WatcherKind,
+        event::{AccessKind, AccessMode},
+    },
+};
+use tracing::{debug, error, warn};
+
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Watches the loaded directories of a tree for entries that appear or vanish.
+///
+/// Every directory is watched on its own and not recursively, so only the part of the file
+/// system that is held in the tree is watched.
+pub struct TreeWatcher {
+    debouncer: Debouncer<ChangeWatcher>,
+    watched: HashSet<PathBuf>,
+}
+
+impl TreeWatcher {
+    /// Create a watcher which calls `on_change` with the directories whose entries changed.
+    pub fn new(mut on_change: impl FnMut(Vec<PathBuf>) + Send + 'static) -> Result<Self> {
+        let config = Config::default().with_timeout(DEBOUNCE_TIMEOUT);
+        let debouncer = new_debouncer_opt(config, move |result: DebounceEventResult| {
+            let events = match result {
+                Ok(events) => events,
+                Err(err) => {
+                    error!("Failed to watch the file tree: {:?}", err);
+                    return;
+                }
+            };
+
+            // an event is reported for the entry that changed, which is listed by its parent
+            let dirs: BTreeSet<PathBuf> = events
+                .iter()
+                .filter_map(|event| event.path.parent())
+                .map(Path::to_path_buf)
+                .collect();
+            if !dirs.is_empty() {
+                on_change(dirs.into_iter().collect());
+            }
+        })?;
+
+        Ok(Self {
+            debouncer,
+            watched: HashSet::new(),
+        })
+    }
+
+    pub fn watch(&mut self, dir: &Path) {
+        if self.watched.contains(dir) {
+            return;
+        }
+        match self
+            .debouncer
+            .watcher()
+            .watch(dir, RecursiveMode::NonRecursive)
+        {
+            Ok(()) => {
+                debug!("Watching {:?}", dir);
+                self.watched.insert(dir.to_path_buf());
+            }
+            Err(err) => warn!("Changes in {:?} are not watched: {}", dir, err),
+        }
+    }
+
+    /// Stop watching `dir` and all watched directories below it.
+    pub fn unwatch(&mut self, dir: &Path) {
+        let unwatched: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|watched| watched.starts_with(dir))
+            .cloned()
+            .collect();
+
+        for path in unwatched {
+            self.watched.remove(&path);
+            // the watch is already gone if the directory was deleted
+            let _ = self.debouncer.watcher().unwatch(&path);
+        }
+    }
+}
+
+/// A [`RecommendedWatcher`] which drops the events of entries that were only opened or read,
+/// e.g. for a preview or by reading the git status, so reading an entry is not a change.
+struct ChangeWatcher(RecommendedWatcher);
+
+impl Watcher for ChangeWatcher {
+    fn new<F: EventHandler>(mut event_handler: F, config: notify::Config) -> notify::Result<Self> {
+        let watcher = RecommendedWatcher::new(
+            move |event: notify::Result<Event>| {
+                let only_read = event.as_ref().is_ok_and(|event| {
+                    matches!(event.kind, EventKind::Access(access)
+                        if access != AccessKind::Close(AccessMode::Write))
+                });
+                if !only_read {
+                    event_handler.handle_event(event);
+                }
+            },
+            config,
+        )?;
+        Ok(Self(watcher))
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> notify::Result<()> {
+        self.0.watch(path, recursive_mode)
+    }
+
+    fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        self.0.unwatch(path)
+    }
+
+    fn kind() -> WatcherKind {
+        RecommendedWatcher::kind()
+    }
+}